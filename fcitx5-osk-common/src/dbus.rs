@@ -92,6 +92,10 @@ pub mod client {
         #[tracing::instrument(level = "debug", skip(self), err, ret)]
         fn process_key_event(&self, serial: u64, keycode: u16, is_release: bool)
             -> ZbusResult<u64>;
+
+        /// Returns the max hold time in milliseconds.
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        fn renew_lease(&self) -> ZbusResult<u64>;
     }
 
     #[derive(Clone, Debug, Getters)]
@@ -0,0 +1,137 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context as _, Result};
+use zbus::{
+    fdo::DBusProxy,
+    message::Header,
+    names::{BusName, UniqueName},
+    proxy::CacheProperties,
+    zvariant::OwnedObjectPath,
+    Connection,
+};
+
+use crate::dbus::client::{LogindManagerServiceProxy, LogindSessionServiceProxy};
+
+/// How long a decision is cached, a change of sessions, e.g. switching users, is applied after it.
+const DECISION_TTL: Duration = Duration::from_secs(5);
+
+/// Authorizes callers of the helper, only users with an active local session on a seat are
+/// allowed, so that remote users on a shared machine can't type into the local session.
+pub struct Authorizer {
+    connection: Connection,
+    dbus: DBusProxy<'static>,
+    logind: LogindManagerServiceProxy<'static>,
+    allowed_users: Vec<String>,
+    /// Decisions of senders, a sender is identified by its unique D-Bus name.
+    decisions: HashMap<String, (Instant, Result<(), String>)>,
+}
+
+impl Authorizer {
+    pub async fn new(connection: &Connection, allowed_users: &[String]) -> Result<Self> {
+        let dbus = DBusProxy::new(connection).await?;
+        let logind = LogindManagerServiceProxy::new(connection).await?;
+        Ok(Self {
+            connection: connection.clone(),
+            dbus,
+            logind,
+            allowed_users: allowed_users.to_vec(),
+            decisions: HashMap::new(),
+        })
+    }
+
+    /// Returns the reason if the sender of the message is denied. The decision is cached for a
+    /// while, so each key event doesn't wait for logind.
+    pub async fn check(&mut self, header: &Header<'_>) -> Result<(), String> {
+        let sender = header
+            .sender()
+            .ok_or_else(|| "message without sender".to_string())?;
+        let now = Instant::now();
+        if let Some((decided_at, decision)) = self.decisions.get(sender.as_str()) {
+            if now.duration_since(*decided_at) < DECISION_TTL {
+                return decision.clone();
+            }
+        }
+        let decision = self.check_sender(sender).await;
+        self.decisions
+            .retain(|_, (t, _)| now.duration_since(*t) < DECISION_TTL);
+        self.decisions
+            .insert(sender.to_string(), (now, decision.clone()));
+        decision
+    }
+
+    /// Forget the decision of a sender, e.g. it is disconnected.
+    pub fn forget(&mut self, sender: &str) {
+        self.decisions.remove(sender);
+    }
+
+    async fn check_sender(&self, sender: &UniqueName<'_>) -> Result<(), String> {
+        let credentials = self
+            .dbus
+            .get_connection_credentials(BusName::from(sender.clone()))
+            .await
+            .map_err(|e| format!("unable to get credentials of {sender}: {e}"))?;
+        let (Some(uid), Some(pid)) = (credentials.unix_user_id(), credentials.process_id()) else {
+            return Err(format!("no uid or pid in credentials of {sender}"));
+        };
+        let user = match self.authorized_user(uid, pid).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                return Err(format!(
+                    "uid: {uid}, pid: {pid} of {sender} has no active local session"
+                ))
+            }
+            Err(e) => {
+                return Err(format!(
+                    "unable to get sessions of uid: {uid}, pid: {pid} of {sender}: {e:#}"
+                ))
+            }
+        };
+        if !self.allowed_users.is_empty() && !self.allowed_users.contains(&user) {
+            return Err(format!(
+                "user: {user}, pid: {pid} of {sender} is not in allowed_users"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the user name if the caller is in an active local session. A caller outside any
+    /// session, e.g. a systemd user service, is checked by the other sessions of its user.
+    async fn authorized_user(&self, uid: u32, pid: u32) -> Result<Option<String>> {
+        match self.logind.get_session_by_pid(pid).await {
+            Ok(path) => return self.session_user(path, uid).await,
+            Err(e) => tracing::debug!("pid: {pid} doesn't belong to a session: {e}"),
+        }
+        for (_, session_uid, _, _, path) in self.logind.list_sessions().await? {
+            if session_uid != uid {
+                continue;
+            }
+            if let Some(user) = self.session_user(path, uid).await? {
+                return Ok(Some(user));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn session_user(&self, path: OwnedObjectPath, uid: u32) -> Result<Option<String>> {
+        let session = LogindSessionServiceProxy::builder(&self.connection)
+            .path(path)?
+            // the state of a session can change at any time.
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        let (session_uid, _) = session.user().await?;
+        let (seat, _) = session.seat().await?;
+        if session_uid != uid
+            || seat.is_empty()
+            || session.remote().await?
+            || !session.active().await?
+        {
+            return Ok(None);
+        }
+        let name = session.name().await.context("unable to get user name")?;
+        Ok(Some(name))
+    }
+}
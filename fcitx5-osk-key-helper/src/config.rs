@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Result;
 use figment::{
@@ -21,6 +21,18 @@ pub struct Config {
     #[getset(get = "pub")]
     #[serde(default = "default_keycodes")]
     keycodes: Vec<u16>,
+
+    /// Users allowed to call the helper, they still need an active local session. All users with
+    /// an active local session are allowed if it is empty.
+    #[getset(get = "pub")]
+    #[serde(default)]
+    allowed_users: Vec<String>,
+
+    /// The lease timeout of a client, keys of a client are released if it doesn't renew its
+    /// lease within this duration. A live client renewing its lease can hold keys forever.
+    #[getset(get_copy = "pub")]
+    #[serde(with = "humantime_serde", default = "default_max_hold_time")]
+    max_hold_time: Duration,
 }
 
 impl Config {
@@ -34,6 +46,10 @@ impl Config {
     }
 }
 
+fn default_max_hold_time() -> Duration {
+    Duration::from_secs(10)
+}
+
 fn default_keycodes() -> Vec<u16> {
     vec![
         37,  // Left Ctrl
@@ -1,23 +1,190 @@
+pub mod client {
+    use zbus::{zvariant::OwnedObjectPath, Result as ZbusResult};
+
+    /// A session in (session id, uid, user name, seat id, session path).
+    pub type SessionInfo = (String, u32, String, String, OwnedObjectPath);
+
+    #[zbus::proxy(
+        default_service = "org.freedesktop.login1",
+        default_path = "/org/freedesktop/login1",
+        interface = "org.freedesktop.login1.Manager"
+    )]
+    pub trait LogindManagerService {
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        #[zbus(name = "GetSessionByPID")]
+        fn get_session_by_pid(&self, pid: u32) -> ZbusResult<OwnedObjectPath>;
+
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        fn list_sessions(&self) -> ZbusResult<Vec<SessionInfo>>;
+    }
+
+    #[zbus::proxy(
+        default_service = "org.freedesktop.login1",
+        interface = "org.freedesktop.login1.Session"
+    )]
+    pub trait LogindSessionService {
+        #[zbus(property)]
+        fn name(&self) -> ZbusResult<String>;
+
+        #[zbus(property)]
+        fn user(&self) -> ZbusResult<(u32, OwnedObjectPath)>;
+
+        #[zbus(property)]
+        fn seat(&self) -> ZbusResult<(String, OwnedObjectPath)>;
+
+        #[zbus(property)]
+        fn active(&self) -> ZbusResult<bool>;
+
+        #[zbus(property)]
+        fn remote(&self) -> ZbusResult<bool>;
+    }
+}
+
 mod server {
-    use zbus::{fdo::Error, message::Header, Connection};
+    use std::{
+        collections::HashMap,
+        time::{Duration, Instant},
+    };
+
+    use futures_util::StreamExt;
+    use tokio::time;
+    use zbus::{
+        fdo::{DBusProxy, Error},
+        message::Header,
+        names::BusName,
+        Connection,
+    };
 
-    use crate::keyboard::Keyboard;
+    use crate::{auth::Authorizer, keyboard::Keyboard};
+
+    struct ClientState {
+        serial: u64,
+        /// Pressed keys of the client are released after it.
+        lease_deadline: Instant,
+    }
 
     pub struct Fcitx5OskKeyHelperControllerService {
         keyboard: Keyboard,
-        serial: u64,
+        authorizer: Authorizer,
+        max_hold_time: Duration,
+        /// A client is identified by its unique D-Bus name.
+        clients: HashMap<String, ClientState>,
     }
 
     impl Fcitx5OskKeyHelperControllerService {
-        pub fn new(keyboard: Keyboard) -> Self {
+        pub fn new(keyboard: Keyboard, authorizer: Authorizer, max_hold_time: Duration) -> Self {
             Self {
                 keyboard,
-                serial: 0,
+                authorizer,
+                max_hold_time,
+                clients: HashMap::new(),
+            }
+        }
+
+        fn sender(header: &Header<'_>) -> Result<String, Error> {
+            header
+                .sender()
+                .map(|s| s.to_string())
+                .ok_or_else(|| Error::InvalidArgs("Missing sender".to_string()))
+        }
+
+        /// Change the serial of a client, its lease is renewed too.
+        fn next_serial(&mut self, client: &str) -> u64 {
+            let serial = rand::random();
+            let old_serial = self
+                .clients
+                .insert(
+                    client.to_string(),
+                    ClientState {
+                        serial,
+                        lease_deadline: Instant::now() + self.max_hold_time,
+                    },
+                )
+                .map(|s| s.serial);
+            tracing::info!(
+                "Serial of client[{}] is changed from {:?} to {}",
+                client,
+                old_serial,
+                serial
+            );
+            serial
+        }
+
+        /// Release keys of a client and forget its serial.
+        fn remove_client(&mut self, client: &str) {
+            self.authorizer.forget(client);
+            if self.clients.remove(client).is_some() {
+                tracing::info!("Client[{}] is gone, release its keys", client);
+                self.keyboard.reset(client);
+            }
+        }
+
+        /// Release keys of clients whose lease is expired, e.g. a client crashed while holding
+        /// a modifier.
+        fn release_expired_keys(&mut self) {
+            let now = Instant::now();
+            for (client, state) in &self.clients {
+                if state.lease_deadline > now {
+                    continue;
+                }
+                let keycodes = self.keyboard.pressed_keycodes(client).to_vec();
+                if !keycodes.is_empty() {
+                    tracing::warn!(
+                        "Lease of client[{}] is expired, release keycodes: {:?}",
+                        client,
+                        keycodes
+                    );
+                    self.keyboard.reset(client);
+                }
+            }
+        }
+
+        async fn authorize(&mut self, header: &Header<'_>, method: &str) -> Result<(), Error> {
+            if let Err(reason) = self.authorizer.check(header).await {
+                tracing::warn!("Deny {} request, {}", method, reason);
+                return Err(Error::AccessDenied(format!("{method} is denied")));
             }
+            Ok(())
         }
 
         pub async fn start(self, conn: &Connection) -> Result<(), Error> {
+            // Expired keys are held at most 1.5 times of max hold time.
+            let watchdog_period = (self.max_hold_time / 2).max(Duration::from_millis(100));
+            let dbus_proxy = DBusProxy::new(conn).await?;
+            let mut stream = dbus_proxy.receive_name_owner_changed().await?;
             conn.object_server().at(Self::OBJECT_PATH, self).await?;
+            let iface_ref = conn
+                .object_server()
+                .interface::<_, Self>(Self::OBJECT_PATH)
+                .await?;
+            tokio::spawn({
+                let iface_ref = iface_ref.clone();
+                async move {
+                    let mut interval = time::interval(watchdog_period);
+                    loop {
+                        interval.tick().await;
+                        iface_ref.get_mut().await.release_expired_keys();
+                    }
+                }
+            });
+            tokio::spawn(async move {
+                while let Some(signal) = stream.next().await {
+                    let args = match signal.args() {
+                        Ok(args) => args,
+                        Err(e) => {
+                            tracing::warn!("Invalid NameOwnerChanged signal: {:?}", e);
+                            continue;
+                        }
+                    };
+                    // A client is gone when its unique name loses the owner.
+                    if let BusName::Unique(name) = args.name() {
+                        if args.new_owner().is_none() {
+                            iface_ref.get_mut().await.remove_client(name.as_str());
+                        }
+                    }
+                }
+                tracing::debug!("NameOwnerChanged stream is closed");
+            });
             conn.request_name(Self::SERVICE_NAME).await?;
             Ok(())
         }
@@ -28,41 +195,63 @@ mod server {
         const SERVICE_NAME: &'static str = "fyi.fortime.Fcitx5OskKeyHelper";
         const OBJECT_PATH: &'static str = "/fyi/fortime/Fcitx5OskKeyHelper/Controller";
 
-        fn next_serial(&mut self) -> u64 {
-            let old_serial = self.serial;
-            self.serial = rand::random();
-            tracing::info!("Serial is changed from {} to {}", old_serial, self.serial);
-            self.serial
-        }
-
         #[tracing::instrument(level = "debug", skip(self), err, ret)]
         pub async fn reset_serial(
             &mut self,
             #[zbus(header)] header: Header<'_>,
         ) -> Result<u64, Error> {
             tracing::info!("Reset serial request from sender: {:?}", header.sender(),);
-            // Reset all pressed keys
-            self.keyboard.reset();
-            Ok(self.next_serial())
+            self.authorize(&header, "reset_serial").await?;
+            let client = Self::sender(&header)?;
+            // Reset pressed keys of the client
+            self.keyboard.reset(&client);
+            Ok(self.next_serial(&client))
+        }
+
+        /// Keep pressed keys of the client held, it returns the max hold time in milliseconds,
+        /// the lease should be renewed before it.
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        pub async fn renew_lease(
+            &mut self,
+            #[zbus(header)] header: Header<'_>,
+        ) -> Result<u64, Error> {
+            self.authorize(&header, "renew_lease").await?;
+            let client = Self::sender(&header)?;
+            let Some(state) = self.clients.get_mut(&client) else {
+                return Err(Error::InvalidArgs(format!(
+                    "Client[{client}] doesn't have a serial"
+                )));
+            };
+            state.lease_deadline = Instant::now() + self.max_hold_time;
+            Ok(self.max_hold_time.as_millis() as u64)
         }
 
         #[tracing::instrument(level = "debug", skip(self), err, ret)]
         pub async fn process_key_event(
             &mut self,
+            #[zbus(header)] header: Header<'_>,
             serial: u64,
             keycode: u16,
             is_release: bool,
         ) -> Result<u64, Error> {
-            if serial != self.serial {
+            self.authorize(&header, "process_key_event").await?;
+            let client = Self::sender(&header)?;
+            let expected = self.clients.get(&client).map(|s| s.serial);
+            if expected != Some(serial) {
                 tracing::warn!(
-                    "Process key event, expect serial: {}, but {}",
-                    self.serial,
+                    "Process key event of client[{}], expect serial: {:?}, but {}",
+                    client,
+                    expected,
                     serial
                 );
                 return Err(Error::InvalidArgs("Invalid serial".to_string()));
             }
-            match self.keyboard.process_key_event(keycode, is_release).await {
-                Ok(true) => Ok(self.next_serial()),
+            match self
+                .keyboard
+                .process_key_event(&client, keycode, is_release)
+                .await
+            {
+                Ok(true) => Ok(self.next_serial(&client)),
                 Ok(false) => Err(Error::InvalidArgs(format!(
                     "Unsupported keycode: {}",
                     keycode
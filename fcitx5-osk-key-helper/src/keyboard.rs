@@ -1,10 +1,64 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
 use evdev::{uinput::VirtualDevice, AttributeSet, KeyCode, KeyEvent};
 
+/// Keycodes pressed by each client. A keycode is shared by clients, it is pressed by the first
+/// client pressing it and released by the last client releasing it.
+#[derive(Default)]
+struct PressedKeys(HashMap<String, Vec<u16>>);
+
+impl PressedKeys {
+    fn count(&self, keycode: u16) -> usize {
+        self.0.values().flatten().filter(|k| **k == keycode).count()
+    }
+
+    /// Returns whether the press event should be emitted. A client pressing a keycode it holds
+    /// already, e.g. the key repeats, doesn't hold it twice.
+    fn press(&mut self, client: &str, keycode: u16) -> bool {
+        let pressed_keycodes = self.0.entry(client.to_string()).or_default();
+        if pressed_keycodes.contains(&keycode) {
+            return false;
+        }
+        pressed_keycodes.push(keycode);
+        self.count(keycode) == 1
+    }
+
+    /// Returns whether the release event should be emitted.
+    fn release(&mut self, client: &str, keycode: u16) -> bool {
+        if let Some(pressed_keycodes) = self.0.get_mut(client) {
+            if let Some(pos) = pressed_keycodes.iter().position(|k| *k == keycode) {
+                pressed_keycodes.swap_remove(pos);
+            }
+            if pressed_keycodes.is_empty() {
+                self.0.remove(client);
+            }
+        }
+        self.count(keycode) == 0
+    }
+
+    fn get(&self, client: &str) -> &[u16] {
+        self.0.get(client).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Forget keycodes of a client, returns keycodes which should be released.
+    fn reset(&mut self, client: &str) -> Vec<u16> {
+        let mut keycodes = self.0.remove(client).unwrap_or_default();
+        keycodes.sort_unstable();
+        keycodes.dedup();
+        keycodes.retain(|k| self.count(*k) == 0);
+        keycodes
+    }
+
+    fn clients(&self) -> Vec<String> {
+        self.0.keys().cloned().collect()
+    }
+}
+
 pub struct Keyboard {
     device: VirtualDevice,
     keycodes: Vec<u16>,
-    pressed_keycodes: Vec<u16>,
+    pressed_keys: PressedKeys,
 }
 
 impl Keyboard {
@@ -21,34 +75,45 @@ impl Keyboard {
         Ok(Keyboard {
             device,
             keycodes: keycodes.to_vec(),
-            pressed_keycodes: vec![],
+            pressed_keys: PressedKeys::default(),
         })
     }
 
-    pub async fn process_key_event(&mut self, keycode: u16, is_release: bool) -> Result<bool> {
+    /// Keys are pressed per client, a keycode held by other clients isn't released.
+    pub async fn process_key_event(
+        &mut self,
+        client: &str,
+        keycode: u16,
+        is_release: bool,
+    ) -> Result<bool> {
         // X11 keycodes are +8 shift of evdev keycodes
         if !self.keycodes.contains(&keycode) || keycode < 8 {
             return Ok(false);
         }
-        let event = if is_release {
-            if let Some(pos) = self.pressed_keycodes.iter().position(|k| *k == keycode) {
-                self.pressed_keycodes.swap_remove(pos);
-            }
-            KeyEvent::new(KeyCode(keycode - 8), 0)
+        let emit = if is_release {
+            self.pressed_keys.release(client, keycode)
         } else {
             // Save which keycode is pressed
-            self.pressed_keycodes.push(keycode);
-            KeyEvent::new(KeyCode(keycode - 8), 1)
+            self.pressed_keys.press(client, keycode)
         };
-        self.device.emit(&[*event])?;
+        if emit {
+            let event = KeyEvent::new(KeyCode(keycode - 8), if is_release { 0 } else { 1 });
+            self.device.emit(&[*event])?;
+        } else {
+            tracing::debug!("Keycode[{keycode}] is held by other clients");
+        }
         Ok(true)
     }
 
-    /// Emit release event for remaining keycodes
-    pub fn reset(&mut self) -> (usize, usize) {
+    pub fn pressed_keycodes(&self, client: &str) -> &[u16] {
+        self.pressed_keys.get(client)
+    }
+
+    /// Emit release event for remaining keycodes of a client
+    pub fn reset(&mut self, client: &str) -> (usize, usize) {
         let mut suc = 0;
         let mut fail = 0;
-        for keycode in self.pressed_keycodes.drain(..) {
+        for keycode in self.pressed_keys.reset(client) {
             let event = KeyEvent::new(KeyCode(keycode - 8), 0);
             if let Err(e) = self.device.emit(&[*event]) {
                 fail += 1;
@@ -57,13 +122,79 @@ impl Keyboard {
                 suc += 1;
             }
         }
-        tracing::debug!("Release key: success[{suc}], fail[{fail}]");
+        tracing::debug!("Release key of client[{client}]: success[{suc}], fail[{fail}]");
         (suc, fail)
     }
+
+    /// Emit release event for remaining keycodes of all clients
+    pub fn reset_all(&mut self) {
+        for client in self.pressed_keys.clients() {
+            self.reset(&client);
+        }
+    }
 }
 
 impl Drop for Keyboard {
     fn drop(&mut self) {
-        self.reset();
+        self.reset_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PressedKeys;
+
+    const LEFT_CTRL: u16 = 37;
+    const LEFT_SHIFT: u16 = 50;
+
+    #[test]
+    fn shared_keycode_is_released_by_the_last_client() {
+        let mut pressed_keys = PressedKeys::default();
+        assert!(pressed_keys.press(":1.1", LEFT_CTRL));
+        assert!(!pressed_keys.press(":1.2", LEFT_CTRL));
+        assert!(!pressed_keys.release(":1.1", LEFT_CTRL));
+        assert!(pressed_keys.release(":1.2", LEFT_CTRL));
+        assert!(pressed_keys.get(":1.1").is_empty());
+        assert!(pressed_keys.get(":1.2").is_empty());
+    }
+
+    #[test]
+    fn disconnected_client_releases_keycodes_not_held_by_others() {
+        let mut pressed_keys = PressedKeys::default();
+        pressed_keys.press(":1.1", LEFT_CTRL);
+        pressed_keys.press(":1.1", LEFT_SHIFT);
+        pressed_keys.press(":1.2", LEFT_CTRL);
+        assert_eq!(pressed_keys.reset(":1.1"), vec![LEFT_SHIFT]);
+        assert!(pressed_keys.get(":1.1").is_empty());
+        assert_eq!(pressed_keys.get(":1.2"), &[LEFT_CTRL]);
+        assert_eq!(pressed_keys.reset(":1.2"), vec![LEFT_CTRL]);
+        assert!(pressed_keys.clients().is_empty());
+    }
+
+    #[test]
+    fn release_from_other_client_keeps_the_keycode_held() {
+        let mut pressed_keys = PressedKeys::default();
+        pressed_keys.press(":1.1", LEFT_CTRL);
+        assert!(!pressed_keys.release(":1.2", LEFT_CTRL));
+        assert_eq!(pressed_keys.get(":1.1"), &[LEFT_CTRL]);
+        assert!(pressed_keys.get(":1.2").is_empty());
+        // the client which pressed it can still release it.
+        assert!(pressed_keys.release(":1.1", LEFT_CTRL));
+    }
+
+    #[test]
+    fn repeated_press_is_released_once() {
+        let mut pressed_keys = PressedKeys::default();
+        assert!(pressed_keys.press(":1.1", LEFT_CTRL));
+        assert!(!pressed_keys.press(":1.1", LEFT_CTRL));
+        assert!(pressed_keys.release(":1.1", LEFT_CTRL));
+        assert!(pressed_keys.clients().is_empty());
+    }
+
+    #[test]
+    fn stray_release_of_an_unheld_keycode_is_emitted() {
+        let mut pressed_keys = PressedKeys::default();
+        assert!(pressed_keys.release(":1.1", LEFT_CTRL));
+        assert!(pressed_keys.clients().is_empty());
     }
 }
@@ -5,8 +5,9 @@ use clap::Parser;
 use dbus::Fcitx5OskKeyHelperControllerService;
 use zbus::Connection;
 
-use crate::{config::Config, keyboard::Keyboard};
+use crate::{auth::Authorizer, config::Config, keyboard::Keyboard};
 
+mod auth;
 mod config;
 mod dbus;
 mod keyboard;
@@ -35,7 +36,8 @@ async fn run(args: Args) -> Result<()> {
     let keyboard = Keyboard::new(config.keycodes())?;
 
     let conn = Connection::system().await?;
-    Fcitx5OskKeyHelperControllerService::new(keyboard)
+    let authorizer = Authorizer::new(&conn, config.allowed_users()).await?;
+    Fcitx5OskKeyHelperControllerService::new(keyboard, authorizer, config.max_hold_time())
         .start(&conn)
         .await?;
 
@@ -2,11 +2,12 @@ use std::{
     cell::RefCell,
     future::{self, Future},
     os::fd::{AsRawFd, OwnedFd},
+    path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 use anyhow::{Context, Error, Result};
@@ -24,11 +25,12 @@ use iced::{
     Color, Element, Event as IcedEvent, Subscription, Task, Theme,
 };
 use iced_futures::event;
-use tokio::time;
+use tokio::{fs, time};
+use xkeysym::Keysym;
 use zbus::Connection;
 
 use crate::{
-    config::{Config, ConfigManager},
+    config::ConfigManager,
     dbus::{
         client::{Fcitx5Services, FdoPortalSettingsServiceProxy},
         server::{
@@ -37,9 +39,11 @@ use crate::{
         },
     },
     state::{
-        CloseOpSource, ImEvent, KeyEvent, KeyboardEvent, LayoutEvent, State, StateExtractor,
-        StoreEvent, ThemeEvent, UpdateConfigEvent, WindowEvent, WindowManagerEvent,
+        ClipboardEvent, CloseOpSource, ImEvent, KeyEvent, KeyboardEvent, LayoutEvent, PredictEvent,
+        State, StateExtractor, StoreEvent, ThemeEvent, UpdateConfigEvent, WindowEvent,
+        WindowManagerEvent,
     },
+    store::Store,
     window::{self, WindowAppearance, WindowManager},
 };
 
@@ -49,6 +53,7 @@ pub mod x11;
 #[derive(Clone, Debug)]
 pub enum Message {
     AfterError,
+    ClipboardEvent(ClipboardEvent),
     Error(KeyboardError),
     Fcitx5VirtualkeyboardImPanelEvent(Fcitx5VirtualkeyboardImPanelEvent),
     ImEvent(ImEvent),
@@ -57,6 +62,7 @@ pub enum Message {
     KeyboardEvent(KeyboardEvent),
     LayoutEvent(LayoutEvent),
     Nothing,
+    PredictEvent(PredictEvent),
     StoreEvent(StoreEvent),
     ThemeEvent(ThemeEvent),
     UpdateConfigEvent(UpdateConfigEvent),
@@ -156,15 +162,17 @@ pub struct AsyncAppState {
     rx: RefCell<Option<UnboundedReceiver<Message>>>,
     display_socket: Option<OwnedFd>,
     detect_theme_enabled: Arc<AtomicBool>,
+    watched_folders: Arc<Mutex<Vec<PathBuf>>>,
 }
 
 impl AsyncAppState {
     pub async fn new(
-        config: &Config,
+        config_manager: &ConfigManager,
         wait_for_socket: bool,
         modifier_workaround: bool,
         shutdown_flag: ShutdownFlag,
     ) -> Result<Self> {
+        let config = config_manager.as_ref();
         let (tx, rx) = mpsc::unbounded();
         let (socket_env_tx, socket_env_rx) = if wait_for_socket {
             let (tx, rx) = oneshot::channel();
@@ -198,9 +206,16 @@ impl AsyncAppState {
         .await?;
         let detect_theme_enabled = Arc::new(AtomicBool::new(false));
         tokio::spawn(detect_theme(
+            tx.clone(),
+            shutdown_flag.clone(),
+            detect_theme_enabled.clone(),
+        ));
+        let watched_folders = Arc::new(Mutex::new(Store::folders(config)));
+        tokio::spawn(watch_config_files(
             tx,
             shutdown_flag,
-            detect_theme_enabled.clone(),
+            config_manager.path().cloned(),
+            watched_folders.clone(),
         ));
         Ok(Self {
             fcitx5_services,
@@ -208,6 +223,7 @@ impl AsyncAppState {
             rx: RefCell::new(Some(rx)),
             display_socket,
             detect_theme_enabled,
+            watched_folders,
         })
     }
 }
@@ -238,10 +254,17 @@ impl<WM> Keyboard<WM> {
             rx,
             display_socket,
             detect_theme_enabled,
+            watched_folders,
         } = async_state;
 
         fcitx5_osk_service_client.set_manual_mode(config_manager.as_ref().manual_mode());
-        let state = State::new(config_manager, wm, fcitx5_services, detect_theme_enabled);
+        let state = State::new(
+            config_manager,
+            wm,
+            fcitx5_services,
+            detect_theme_enabled,
+            watched_folders,
+        );
         let mut init_task = Task::done(StoreEvent::Load.into());
         if !wait_for_socket {
             // open indicator if it is not waiting for a socket.
@@ -435,7 +458,12 @@ where
                     Fcitx5VirtualkeyboardImPanelEvent::UpdateCandidateArea(state) => {
                         self.state.im_mut().update_candidate_area_state(state);
                     }
-                    _ => {}
+                    Fcitx5VirtualkeyboardImPanelEvent::UpdatePreeditArea(text) => {
+                        self.state.im_mut().update_preedit_text(text);
+                    }
+                    Fcitx5VirtualkeyboardImPanelEvent::UpdatePreeditCaret(caret) => {
+                        self.state.im_mut().update_preedit_caret(caret);
+                    }
                 }
             }
             Message::ImPanelEvent(event) => {
@@ -480,6 +508,12 @@ where
             Message::ImEvent(event) => {
                 task = task.chain(self.state.on_im_event(event));
             }
+            Message::ClipboardEvent(event) => {
+                task = task.chain(self.state.on_clipboard_event(event));
+            }
+            Message::PredictEvent(event) => {
+                task = task.chain(self.state.on_predict_event(event));
+            }
             Message::UpdateFcitx5Services(fcitx5_services) => {
                 tracing::debug!("update fcitx5_services");
                 self.state.update_fcitx5_services(fcitx5_services);
@@ -537,6 +571,77 @@ fn modal<'a>(
     .into()
 }
 
+/// Poll the modified time of the config file and toml files in the folders of the store, then
+/// send a `StoreEvent` to reload them if any is changed.
+async fn watch_config_files(
+    tx: UnboundedSender<Message>,
+    shutdown_flag: ShutdownFlag,
+    config_path: Option<PathBuf>,
+    watched_folders: Arc<Mutex<Vec<PathBuf>>>,
+) {
+    async fn modified(path: &Path) -> Option<SystemTime> {
+        fs::metadata(path).await.and_then(|m| m.modified()).ok()
+    }
+
+    async fn snapshot(folders: &[PathBuf]) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut snapshot = vec![];
+        for folder in folders {
+            let Ok(mut entries) = fs::read_dir(folder).await else {
+                continue;
+            };
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if let Some("toml") = path.extension().and_then(|p| p.to_str()) {
+                    let modified = modified(&path).await;
+                    snapshot.push((path, modified));
+                }
+            }
+        }
+        snapshot.sort_unstable();
+        snapshot
+    }
+
+    let mut config_modified = None;
+    let mut folders_snapshot = None;
+    while !shutdown_flag.get() {
+        let mut event = None;
+        if let Some(config_path) = &config_path {
+            let modified = modified(config_path).await;
+            if config_modified
+                .replace(modified)
+                .is_some_and(|m| m != modified)
+            {
+                event = Some(StoreEvent::LoadConfig);
+            }
+        }
+
+        let folders = match watched_folders.lock() {
+            Ok(folders) => folders.clone(),
+            Err(_) => {
+                tracing::error!("watched folders are poisoned, close the task");
+                break;
+            }
+        };
+        let snapshot = snapshot(&folders).await;
+        if folders_snapshot
+            .replace(snapshot.clone())
+            .is_some_and(|s| s != snapshot)
+        {
+            // the store is loaded after the config is loaded.
+            event.get_or_insert(StoreEvent::Load);
+        }
+
+        if let Some(event) = event {
+            tracing::info!("config files are changed, reload them: {event:?}");
+            if tx.unbounded_send(event.into()).is_err() {
+                tracing::warn!("failed to send StoreEvent message, close the task");
+                break;
+            }
+        }
+        time::sleep(Duration::from_millis(1000)).await;
+    }
+}
+
 async fn detect_theme(
     tx: UnboundedSender<Message>,
     shutdown_flag: ShutdownFlag,
@@ -647,6 +752,24 @@ async fn start_dbus_services(
     Ok(fcitx5_osk_service_client)
 }
 
+/// The keysym of a key event for backends working by keysyms. The keyboard sends some modifiers
+/// by X11 keycodes without keyval, e.g. the shift of a shifted key, they are mapped to the keysyms
+/// of the modifiers.
+fn event_keysym(keyval: u32, keycode: u32) -> Keysym {
+    if keyval != 0 {
+        return Keysym::from(keyval);
+    }
+    match keycode {
+        37 => Keysym::Control_L,
+        105 => Keysym::Control_R,
+        50 => Keysym::Shift_L,
+        62 => Keysym::Shift_R,
+        64 => Keysym::Alt_L,
+        108 => Keysym::Alt_R,
+        _ => Keysym::NoSymbol,
+    }
+}
+
 /// this function should be run in multi_thread runtime, otherwise, it will be deadlocked.
 fn run_async<T, F>(f: F) -> Result<T>
 where
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use clipboard::ClipboardContext;
 use connection::WaylandConnection;
 use fcitx5_osk_common::signal::ShutdownFlag;
 use iced::{futures::stream, window::Id, Element, Subscription, Task, Theme};
@@ -7,11 +8,16 @@ use iced_layershell::{
     settings::{LayerShellSettings, StartMode},
     to_layer_message, Appearance,
 };
+use xkeysym::Keysym;
 
 pub use crate::app::wayland::output::{OutputContext, OutputGeometry};
 use crate::{
-    app::{self, wayland::input_method::InputMethodContext, Keyboard, MapTask, Message},
-    config::ConfigManager,
+    app::{
+        self,
+        wayland::{input_method::InputMethodContext, virtual_keyboard::VirtualKeyboardContext},
+        Keyboard, MapTask, Message,
+    },
+    config::{Backend, ConfigManager},
     font,
     state::WindowManagerEvent,
     window::{wayland::WaylandWindowManager, WindowManagerMode},
@@ -19,9 +25,23 @@ use crate::{
 
 use super::AsyncAppState;
 
+mod clipboard;
 mod connection;
 mod input_method;
+mod keymap;
 mod output;
+mod virtual_keyboard;
+
+/// The mask of a modifier key, it is the same as the one of X11, and the real modifiers of XKB.
+fn modifier_mask(keysym: Keysym) -> Option<u32> {
+    match keysym {
+        Keysym::Shift_L | Keysym::Shift_R => Some(1),
+        Keysym::Control_L | Keysym::Control_R => Some(1 << 2),
+        Keysym::Alt_L | Keysym::Alt_R | Keysym::Meta_L | Keysym::Meta_R => Some(1 << 3),
+        Keysym::Super_L | Keysym::Super_R => Some(1 << 6),
+        _ => None,
+    }
+}
 
 #[to_layer_message(multi)]
 #[derive(Clone, Debug)]
@@ -37,7 +57,10 @@ impl From<Message> for WaylandMessage {
 
 struct WaylandKeyboard {
     input_method_context: InputMethodContext,
+    /// It is used if the backend is `VirtualKeyboard`.
+    virtual_keyboard_context: Option<VirtualKeyboardContext>,
     output_context: OutputContext,
+    clipboard_context: ClipboardContext,
     shutdown_flag: ShutdownFlag,
     inner: Keyboard<WaylandWindowManager>,
 }
@@ -47,6 +70,7 @@ impl WaylandKeyboard {
         config_manager: ConfigManager,
         input_method_context: InputMethodContext,
         output_context: OutputContext,
+        clipboard_context: ClipboardContext,
         wait_for_socket: bool,
         modifier_workaround: bool,
         shutdown_flag: ShutdownFlag,
@@ -59,7 +83,7 @@ impl WaylandKeyboard {
             let shutdown_flag = shutdown_flag.clone();
             async move {
                 let res = AsyncAppState::new(
-                    config_manager.as_ref(),
+                    &config_manager,
                     wait_for_socket,
                     modifier_workaround,
                     shutdown_flag,
@@ -85,7 +109,9 @@ impl WaylandKeyboard {
         (
             Self {
                 input_method_context,
+                virtual_keyboard_context: None,
                 output_context,
+                clipboard_context,
                 shutdown_flag,
                 inner,
             },
@@ -104,6 +130,7 @@ impl WaylandKeyboard {
             self.inner.subscription(),
             self.input_method_context.subscription(),
             self.output_context.subscription(),
+            self.clipboard_context.subscription(),
         ];
 
         // These messages only work in the first call
@@ -115,6 +142,25 @@ impl WaylandKeyboard {
                     .into(),
             );
         }
+        if let Some(virtual_keyboard_context) = &self.virtual_keyboard_context {
+            match virtual_keyboard_context.fcitx5_services() {
+                // switch to our virtual-keyboard-v1 implementation.
+                Ok(fcitx5_services) => {
+                    once_messages.push(Message::UpdateFcitx5Services(fcitx5_services).into())
+                }
+                Err(e) => once_messages.push(
+                    app::fatal_with_context(
+                        e,
+                        "failed to create Fcitx5Services for virtual_keyboard_v1",
+                    )
+                    .into(),
+                ),
+            }
+        }
+        // Not all compositors support wlr-data-control, the clipboard history is just empty.
+        if let Err(e) = self.clipboard_context.listen() {
+            tracing::warn!("Unable to listen to the changes of wayland clipboard: {e:?}");
+        }
         subscriptions.push(Subscription::run_with_id(
             "external::wayland_once",
             stream::iter(once_messages),
@@ -126,7 +172,11 @@ impl WaylandKeyboard {
     pub fn update(&mut self, message: WaylandMessage) -> Task<WaylandMessage> {
         if self.shutdown_flag.get() {
             self.input_method_context.close();
+            if let Some(virtual_keyboard_context) = &self.virtual_keyboard_context {
+                virtual_keyboard_context.close();
+            }
             self.output_context.close();
+            self.clipboard_context.close();
         }
 
         if let WaylandMessage::Inner(message) = message {
@@ -187,7 +237,16 @@ pub fn start(
 
     let connection = WaylandConnection::new();
     let input_method_context = InputMethodContext::new(connection.clone());
+    let virtual_keyboard_context = match config_manager.as_ref().backend() {
+        Backend::VirtualKeyboard => Some(VirtualKeyboardContext::new(connection.clone())),
+        Backend::Fcitx5 => None,
+        backend => {
+            tracing::warn!("backend {backend} isn't supported in Wayland, fcitx5 is used");
+            None
+        }
+    };
     let output_context = OutputContext::new(connection.clone());
+    let clipboard_context = ClipboardContext::new(connection.clone());
 
     build_pattern::daemon(
         clap::crate_name!(),
@@ -215,14 +274,16 @@ pub fn start(
         ..Default::default()
     })
     .run_with(move || {
-        let (keyboard, task) = WaylandKeyboard::new(
+        let (mut keyboard, task) = WaylandKeyboard::new(
             config_manager,
             input_method_context,
             output_context,
+            clipboard_context,
             wait_for_socket,
             modifier_workaround,
             shutdown_flag,
         );
+        keyboard.virtual_keyboard_context = virtual_keyboard_context;
         (keyboard, init_task.chain(task).map_task())
     })?;
     Ok(())
@@ -0,0 +1,315 @@
+use std::{
+    future::Future,
+    io::{self, PipeReader},
+    os::fd::{AsFd as _, OwnedFd},
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use anyhow::Result;
+use iced::{
+    futures::{
+        channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        stream,
+    },
+    Subscription,
+};
+use tokio::{io::AsyncReadExt as _, net::unix::pipe::Receiver, task::JoinHandle, time};
+use wayland_client::{
+    delegate_noop, event_created_child, protocol::wl_seat::WlSeat, Connection, Dispatch, Proxy,
+    QueueHandle,
+};
+use wayland_protocols_wlr::data_control::v1::client::{
+    zwlr_data_control_device_v1::{self, Event as DataControlDeviceEvent, ZwlrDataControlDeviceV1},
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_offer_v1::{Event as DataControlOfferEvent, ZwlrDataControlOfferV1},
+};
+
+use crate::{
+    app::{
+        wayland::{connection::WaylandConnection, WaylandMessage},
+        Message,
+    },
+    state::ClipboardEvent,
+};
+
+/// Mime types of text, the former has higher priority.
+const TEXT_MIME_TYPES: [&str; 4] = [
+    "text/plain;charset=utf-8",
+    "UTF8_STRING",
+    "text/plain",
+    "STRING",
+];
+
+/// Password managers mark their secrets with this mime type, they shouldn't be recorded.
+const SECRET_MIME_TYPE: &str = "x-kde-passwordManagerHint";
+
+/// Texts larger than this are not recorded.
+const MAX_TEXT_LEN: usize = 64 * 1024;
+
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+struct State {
+    rx: Option<UnboundedReceiver<WaylandMessage>>,
+    bg_handle: Option<JoinHandle<()>>,
+    closed: bool,
+}
+
+impl State {
+    fn close(&mut self) {
+        if self.closed {
+            return;
+        }
+        tracing::debug!("Close ClipboardContext State");
+        if let Some(bg_handle) = self.bg_handle.take() {
+            bg_handle.abort();
+        }
+        self.closed = true;
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Watch the clipboard by `wlr-data-control`.
+#[derive(Clone)]
+pub struct ClipboardContext {
+    connection: WaylandConnection,
+    tx: UnboundedSender<WaylandMessage>,
+    state: Arc<Mutex<State>>,
+}
+
+impl ClipboardContext {
+    pub fn new(connection: WaylandConnection) -> Self {
+        let (tx, rx) = mpsc::unbounded();
+        Self {
+            connection,
+            tx,
+            state: Arc::new(Mutex::new(State {
+                rx: Some(rx),
+                bg_handle: None,
+                closed: false,
+            })),
+        }
+    }
+
+    fn state(&self) -> Option<MutexGuard<'_, State>> {
+        self.state.lock().ok()
+    }
+
+    pub fn subscription(&self) -> Subscription<WaylandMessage> {
+        const EXTERNAL_SUBSCRIPTION_ID: &str = "external::wayland_clipboard";
+        if let Some(rx) = self.state().and_then(|mut s| s.rx.take()) {
+            Subscription::run_with_id(EXTERNAL_SUBSCRIPTION_ID, rx)
+        } else {
+            // should always return a subscription with the same id, otherwise, the first one will
+            // be dropped.
+            Subscription::run_with_id(EXTERNAL_SUBSCRIPTION_ID, stream::empty())
+        }
+    }
+
+    pub fn listen(&self) -> Result<()> {
+        let Some(mut guard) = self.state() else {
+            anyhow::bail!("Unable to listen, the state of ClipboardContext is poisoned");
+        };
+        if guard.closed {
+            return Ok(());
+        }
+        if guard.bg_handle.is_none() {
+            let bg = listen(self).inspect_err(|_| {
+                // don't try again, the protocol may not be supported.
+                guard.closed = true;
+            })?;
+            guard.bg_handle = Some(tokio::spawn(async move {
+                if let Err(e) = bg.await {
+                    tracing::error!("wayland clipboard event queue exit with error: {:?}", e);
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        let Some(mut guard) = self.state() else {
+            tracing::debug!("Closing ClipboardContext, but lock is poisoned");
+            return;
+        };
+        guard.close();
+    }
+
+    fn send_copied_event(&self, text: String) {
+        if self
+            .tx
+            .unbounded_send(Message::from(ClipboardEvent::Copied(text)).into())
+            .is_err()
+        {
+            tracing::error!("Unable to send ClipboardEvent::Copied event");
+        }
+    }
+}
+
+struct ClipboardListener {
+    clipboard_context: ClipboardContext,
+    device: ZwlrDataControlDeviceV1,
+    /// Offers and their mime types.
+    offers: Vec<(ZwlrDataControlOfferV1, Vec<String>)>,
+}
+
+impl ClipboardListener {
+    fn take_offer(&mut self, offer: &ZwlrDataControlOfferV1) -> Option<Vec<String>> {
+        let pos = self.offers.iter().position(|(o, _)| o == offer)?;
+        Some(self.offers.swap_remove(pos).1)
+    }
+
+    fn receive(&self, conn: &Connection, offer: &ZwlrDataControlOfferV1, mime_types: Vec<String>) {
+        if mime_types.iter().any(|m| m == SECRET_MIME_TYPE) {
+            tracing::debug!("Skip a secret in the clipboard");
+            return;
+        }
+        let Some(mime_type) = TEXT_MIME_TYPES
+            .iter()
+            .find(|m| mime_types.iter().any(|t| t == *m))
+        else {
+            tracing::debug!("No text in the clipboard, mime types: {:?}", mime_types);
+            return;
+        };
+        let (reader, writer) = match io::pipe() {
+            Ok(p) => p,
+            Err(e) => {
+                tracing::error!("Unable to create a pipe for reading the clipboard: {e:?}");
+                return;
+            }
+        };
+        offer.receive(mime_type.to_string(), writer.as_fd());
+        // close our write end, so we can read to the end.
+        drop(writer);
+        if let Err(e) = conn.flush() {
+            tracing::error!("Unable to flush the request of reading the clipboard: {e:?}");
+            return;
+        }
+        let clipboard_context = self.clipboard_context.clone();
+        tokio::spawn(async move {
+            match read_text(reader).await {
+                Ok(Some(text)) => clipboard_context.send_copied_event(text),
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Unable to read the clipboard: {e:?}"),
+            }
+        });
+    }
+}
+
+impl Drop for ClipboardListener {
+    fn drop(&mut self) {
+        for (offer, _) in self.offers.drain(..) {
+            offer.destroy();
+        }
+        self.device.destroy();
+    }
+}
+
+impl Dispatch<ZwlrDataControlDeviceV1, ()> for ClipboardListener {
+    fn event(
+        state: &mut Self,
+        _proxy: &ZwlrDataControlDeviceV1,
+        event: <ZwlrDataControlDeviceV1 as Proxy>::Event,
+        _data: &(),
+        conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        match event {
+            DataControlDeviceEvent::DataOffer { id } => state.offers.push((id, vec![])),
+            DataControlDeviceEvent::Selection { id: Some(offer) } => {
+                if let Some(mime_types) = state.take_offer(&offer) {
+                    state.receive(conn, &offer, mime_types);
+                }
+                offer.destroy();
+            }
+            // the primary selection isn't recorded
+            DataControlDeviceEvent::PrimarySelection { id: Some(offer) } => {
+                state.take_offer(&offer);
+                offer.destroy();
+            }
+            DataControlDeviceEvent::Finished => {
+                tracing::warn!("The data control device is finished, the clipboard isn't watched");
+            }
+            _ => {}
+        }
+    }
+
+    event_created_child!(ClipboardListener, ZwlrDataControlDeviceV1, [
+        zwlr_data_control_device_v1::EVT_DATA_OFFER_OPCODE => (ZwlrDataControlOfferV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrDataControlOfferV1, ()> for ClipboardListener {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrDataControlOfferV1,
+        event: <ZwlrDataControlOfferV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qhandle: &QueueHandle<Self>,
+    ) {
+        if let DataControlOfferEvent::Offer { mime_type } = event {
+            if let Some((_, mime_types)) = state.offers.iter_mut().find(|(o, _)| o == proxy) {
+                mime_types.push(mime_type);
+            }
+        }
+    }
+}
+
+delegate_noop!(ClipboardListener: ignore WlSeat);
+delegate_noop!(ClipboardListener: ignore ZwlrDataControlManagerV1);
+
+/// Read the text sent by the source, return None if it is too large or not a utf-8 string.
+async fn read_text(reader: PipeReader) -> Result<Option<String>> {
+    let mut receiver = Receiver::from_owned_fd(OwnedFd::from(reader))?;
+    let mut buf = vec![];
+    time::timeout(
+        READ_TIMEOUT,
+        (&mut receiver)
+            .take(MAX_TEXT_LEN as u64 + 1)
+            .read_to_end(&mut buf),
+    )
+    .await??;
+    if buf.len() > MAX_TEXT_LEN {
+        tracing::debug!("Skip a text larger than {} bytes", MAX_TEXT_LEN);
+        return Ok(None);
+    }
+    Ok(String::from_utf8(buf).ok())
+}
+
+fn listen(
+    clipboard_context: &ClipboardContext,
+) -> Result<impl Future<Output = Result<()>> + 'static> {
+    let state = clipboard_context.connection.state()?;
+    let connection = state.connection();
+    let global_list = state.global_list()?;
+
+    let mut event_queue = connection.new_event_queue::<ClipboardListener>();
+    let qh = event_queue.handle();
+
+    let seat = global_list.bind::<WlSeat, _, _>(&qh, 1..=8, ())?;
+    let manager = global_list.bind::<ZwlrDataControlManagerV1, _, _>(&qh, 1..=2, ())?;
+    let device = manager.get_data_device(&seat, &qh, ());
+    // the device keeps working after the manager is destroyed.
+    manager.destroy();
+
+    let mut clipboard_listener = ClipboardListener {
+        clipboard_context: clipboard_context.clone(),
+        device,
+        offers: vec![],
+    };
+    Ok(async move {
+        // don't use blocking_dispatch, it will freeze the eventloop of layershellev
+        loop {
+            std::future::poll_fn(|cx| {
+                event_queue.poll_dispatch_pending(cx, &mut clipboard_listener)
+            })
+            .await?;
+        }
+    })
+}
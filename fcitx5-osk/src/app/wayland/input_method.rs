@@ -122,18 +122,36 @@ mod v1 {
 
     use anyhow::{Context, Result};
     use iced::futures::channel::mpsc::UnboundedSender;
-    use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
-    use wayland_protocols::wp::input_method::zv1::client::{
-        zwp_input_method_context_v1::ZwpInputMethodContextV1,
-        zwp_input_method_v1::{self, Event as ZwpInputMethodV1Event, ZwpInputMethodV1},
+    use wayland_client::{
+        delegate_noop, event_created_child,
+        protocol::{wl_keyboard::KeyState, wl_seat::WlSeat},
+        Connection, Dispatch, Proxy, QueueHandle,
     };
+    use wayland_protocols::wp::{
+        input_method::zv1::client::{
+            zwp_input_method_context_v1::{
+                Event as ZwpInputMethodContextV1Event, ZwpInputMethodContextV1,
+            },
+            zwp_input_method_v1::{self, Event as ZwpInputMethodV1Event, ZwpInputMethodV1},
+        },
+        text_input::zv1::client::zwp_text_input_v1::ContentPurpose as ZwpContentPurpose,
+    };
+    use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+        zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+        zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+    };
+    use xkeysym::Keysym;
     use zbus::{Error as ZbusError, Result as ZbusResult};
 
     use crate::{
         app::{
-            wayland::{connection::WaylandConnection, WaylandMessage},
+            event_keysym,
+            wayland::{
+                connection::WaylandConnection, keymap::Keymap, modifier_mask, WaylandMessage,
+            },
             Message,
         },
+        config::ContentPurpose,
         dbus::{
             client::{
                 IFcitx5ControllerService, IFcitx5VirtualKeyboardBackendService,
@@ -141,8 +159,27 @@ mod v1 {
             },
             server::ImPanelEvent,
         },
+        state::LayoutEvent,
     };
 
+    /// The names of modifiers sent by `modifiers_map`, the modifiers of a keysym are masks of
+    /// their indices.
+    const MODIFIERS_MAP: &[u8] = b"Shift\0Control\0Mod1\0Mod4\0";
+
+    /// The X11 masks of the modifiers in `MODIFIERS_MAP`.
+    const MODIFIER_MASKS: [u32; 4] = [1, 1 << 2, 1 << 3, 1 << 6];
+
+    /// The X11 mask of Control, Alt and Super.
+    const SHORTCUT_MASK: u32 = (1 << 2) | (1 << 3) | (1 << 6);
+
+    fn to_mapped_modifiers(mask: u32) -> u32 {
+        MODIFIER_MASKS
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| mask & **m != 0)
+            .fold(0, |res, (i, _)| res | (1 << i))
+    }
+
     #[derive(Debug)]
     pub struct Fcitx5ControllerServiceStub;
 
@@ -155,7 +192,17 @@ mod v1 {
     #[derive(Debug)]
     pub struct WaylandInputMethodV1Client {
         serial: AtomicU32,
+        /// The serial of the latest text input state, it is updated by the context.
+        text_input_serial: Arc<AtomicU32>,
         input_method_context: Arc<Mutex<Option<ZwpInputMethodContextV1>>>,
+        /// The X11 mask of modifier keys which are pressed.
+        depressed: u32,
+        /// Keys which can't be committed are typed by it if the compositor supports it, the
+        /// context has no keymap of its own.
+        virtual_keyboard: Option<ZwpVirtualKeyboardV1>,
+        keymap: Arc<Mutex<Keymap>>,
+        /// The evdev keycodes of keys which are pressed by the virtual keyboard.
+        pressed: Vec<u32>,
     }
 
     impl WaylandInputMethodV1Client {
@@ -164,11 +211,53 @@ mod v1 {
                 .lock()
                 .expect("wayland input method context v1 is poisoned")
         }
+
+        /// Type a key by the keycode of the keysym in the keymap, the modifiers are applied to
+        /// this key only.
+        fn type_keysym(
+            &mut self,
+            virtual_keyboard: &ZwpVirtualKeyboardV1,
+            keysym: Keysym,
+            modifiers: u32,
+            is_release: bool,
+            time: u32,
+        ) -> ZbusResult<()> {
+            let mut keymap = self.keymap.lock().expect("wayland keymap is poisoned");
+            let (keycode, changed) = keymap.keycode(keysym, &self.pressed);
+            if changed {
+                keymap
+                    .upload(virtual_keyboard)
+                    .map_err(|e| ZbusError::Failure(format!("{e:#}")))?;
+            }
+            virtual_keyboard.modifiers(modifiers, 0, 0, 0);
+            let key_state = if is_release {
+                self.pressed.retain(|k| *k != keycode);
+                KeyState::Released
+            } else {
+                self.pressed.push(keycode);
+                KeyState::Pressed
+            };
+            virtual_keyboard.key(time, keycode, key_state.into());
+            virtual_keyboard.modifiers(self.depressed, 0, 0, 0);
+            Ok(())
+        }
+
+        /// Whether the key of the keysym is pressed by the virtual keyboard, so it is released by
+        /// it too.
+        fn is_pressed(&self, keysym: Keysym) -> bool {
+            let keymap = self.keymap.lock().expect("wayland keymap is poisoned");
+            keymap
+                .find(keysym)
+                .is_some_and(|keycode| self.pressed.contains(&keycode))
+        }
     }
 
     impl Drop for WaylandInputMethodV1Client {
         fn drop(&mut self) {
             tracing::debug!("drop WaylandInputMethodV1Client");
+            if let Some(virtual_keyboard) = self.virtual_keyboard.take() {
+                virtual_keyboard.destroy();
+            }
         }
     }
 
@@ -217,36 +306,66 @@ mod v1 {
             is_release: bool,
             time: u32,
         ) -> ZbusResult<()> {
+            let keysym = event_keysym(keyval, keycode);
+            if let Some(mask) = modifier_mask(keysym) {
+                if is_release {
+                    self.depressed &= !mask;
+                } else {
+                    self.depressed |= mask;
+                }
+            }
             let guard = self.input_method_context();
             let Some(input_method_context) = guard.as_ref() else {
                 return Err(ZbusError::Failure(
                     "there is no wayland input method context v1 ".to_string(),
                 ));
             };
-            // currently, this method is only for inputting password in kscreenlocker, we don't
-            // respect the rule of xkbcommon.
-
-            // only handle shift for inputting captital letter.
-            let modifier_mask = if keycode == 50 || keycode == 62 {
-                // Shift mask is 0x1
-                Some(1)
-            } else {
-                None
-            };
             let serial = self.serial.fetch_add(1, Ordering::Relaxed);
-            if let Some(modifier_mask) = modifier_mask {
-                // this is a simple implementation, no key modifier combo
-                if is_release {
-                    input_method_context.modifiers(serial, 0, 0, 0, 0);
-                } else {
-                    input_method_context.modifiers(serial, modifier_mask, 0, 0, 0);
+            if modifier_mask(keysym).is_some() {
+                input_method_context.modifiers(serial, self.depressed, 0, 0, 0);
+                if let Some(virtual_keyboard) = &self.virtual_keyboard {
+                    virtual_keyboard.modifiers(self.depressed, 0, 0, 0);
                 }
-            } else {
-                let key_state = if is_release { 0 } else { 1 };
-                if keycode != 0 {
+                return Ok(());
+            }
+
+            let key_state = if is_release { 0 } else { 1 };
+            let modifiers = self.depressed | state;
+            // a printable character is committed as a text, so it arrives no matter whether the
+            // keymap of the compositor has it. Keys with modifiers are shortcuts.
+            let c = keysym.key_char().filter(|c| !c.is_control());
+            let committed = c.is_some() && modifiers & SHORTCUT_MASK == 0;
+            if let Some(virtual_keyboard) = self.virtual_keyboard.clone() {
+                if keysym != Keysym::NoSymbol && (!committed || self.is_pressed(keysym)) {
+                    drop(guard);
+                    return self.type_keysym(
+                        &virtual_keyboard,
+                        keysym,
+                        modifiers,
+                        is_release,
+                        time,
+                    );
+                }
+            }
+            match c {
+                Some(c) if committed => {
+                    if !is_release {
+                        let text_input_serial = self.text_input_serial.load(Ordering::Relaxed);
+                        input_method_context.commit_string(text_input_serial, c.to_string());
+                    }
+                }
+                _ if keycode != 0 => {
                     input_method_context.key(serial, time, keycode - 8, key_state);
-                } else {
-                    input_method_context.keysym(serial, time, keyval, key_state, state);
+                }
+                _ => {
+                    let text_input_serial = self.text_input_serial.load(Ordering::Relaxed);
+                    input_method_context.keysym(
+                        text_input_serial,
+                        time,
+                        keyval,
+                        key_state,
+                        to_mapped_modifiers(modifiers),
+                    );
                 }
             }
             Ok(())
@@ -265,15 +384,29 @@ mod v1 {
         }
 
         async fn reset_pressed_key_events(&mut self) -> ZbusResult<()> {
-            // Since only shift in all modifier keys is handled, there is no need to reset
+            // committed keys are pressed and released at once, only modifiers and keys typed by
+            // the virtual keyboard can be held.
+            self.depressed = 0;
+            if let Some(virtual_keyboard) = &self.virtual_keyboard {
+                for keycode in self.pressed.drain(..) {
+                    virtual_keyboard.key(0, keycode, KeyState::Released.into());
+                }
+                virtual_keyboard.modifiers(0, 0, 0, 0);
+            }
+            if let Some(input_method_context) = self.input_method_context().as_ref() {
+                let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+                input_method_context.modifiers(serial, 0, 0, 0, 0);
+            }
             Ok(())
         }
     }
 
     struct WaylandInputMethodV1Server {
-        #[allow(unused)]
         tx: UnboundedSender<WaylandMessage>,
+        text_input_serial: Arc<AtomicU32>,
         input_method_context: Arc<Mutex<Option<ZwpInputMethodContextV1>>>,
+        virtual_keyboard: Option<ZwpVirtualKeyboardV1>,
+        keymap: Arc<Mutex<Keymap>>,
     }
 
     impl WaylandInputMethodV1Server {
@@ -293,15 +426,61 @@ mod v1 {
         }
     }
 
+    fn to_content_purpose(purpose: u32) -> ContentPurpose {
+        match ZwpContentPurpose::try_from(purpose) {
+            Ok(ZwpContentPurpose::Normal) => ContentPurpose::Normal,
+            Ok(ZwpContentPurpose::Alpha) => ContentPurpose::Alpha,
+            Ok(ZwpContentPurpose::Digits) => ContentPurpose::Digits,
+            Ok(ZwpContentPurpose::Number) => ContentPurpose::Number,
+            Ok(ZwpContentPurpose::Phone) => ContentPurpose::Phone,
+            Ok(ZwpContentPurpose::Url) => ContentPurpose::Url,
+            Ok(ZwpContentPurpose::Email) => ContentPurpose::Email,
+            Ok(ZwpContentPurpose::Name) => ContentPurpose::Name,
+            Ok(ZwpContentPurpose::Password) => ContentPurpose::Password,
+            Ok(ZwpContentPurpose::Date) => ContentPurpose::Date,
+            Ok(ZwpContentPurpose::Time) => ContentPurpose::Time,
+            Ok(ZwpContentPurpose::Datetime) => ContentPurpose::Datetime,
+            Ok(ZwpContentPurpose::Terminal) => ContentPurpose::Terminal,
+            _ => {
+                tracing::debug!("unknown content purpose: {purpose}");
+                ContentPurpose::Normal
+            }
+        }
+    }
+
     impl Dispatch<ZwpInputMethodContextV1, ()> for WaylandInputMethodV1Server {
         fn event(
-            _state: &mut Self,
-            _proxy: &ZwpInputMethodContextV1,
-            _event: <ZwpInputMethodContextV1 as Proxy>::Event,
+            state: &mut Self,
+            proxy: &ZwpInputMethodContextV1,
+            event: <ZwpInputMethodContextV1 as Proxy>::Event,
             _data: &(),
             _conn: &Connection,
             _qhandle: &QueueHandle<Self>,
         ) {
+            let res = match event {
+                ZwpInputMethodContextV1Event::ContentType { hint, purpose } => {
+                    if state.input_method_context().as_ref() != Some(proxy) {
+                        // the context has been deactivated.
+                        return;
+                    }
+                    let purpose = to_content_purpose(purpose);
+                    tracing::debug!(
+                        "wayland input method context v1 content type, hint: {hint}, purpose: {purpose}"
+                    );
+                    state.tx.unbounded_send(
+                        Message::from(LayoutEvent::UpdateContentPurpose(purpose)).into(),
+                    )
+                }
+                ZwpInputMethodContextV1Event::CommitState { serial } => {
+                    state.text_input_serial.store(serial, Ordering::Relaxed);
+                    Ok(())
+                }
+                // the surrounding text isn't used, the keyboard doesn't edit the text directly.
+                _ => Ok(()),
+            };
+            if res.is_err() {
+                tracing::error!("unable to send wayland input-method-context-v1 event");
+            }
         }
     }
 
@@ -317,6 +496,15 @@ mod v1 {
             let res = match event {
                 ZwpInputMethodV1Event::Activate { id } => {
                     tracing::debug!("wayland input method v1 activate");
+                    id.modifiers_map(MODIFIERS_MAP.to_vec());
+                    if let Some(virtual_keyboard) = &state.virtual_keyboard {
+                        // the keymap of the keyboard of the seat may be changed since the last
+                        // activation, e.g. by a hardware keyboard.
+                        let keymap = state.keymap.lock().expect("wayland keymap is poisoned");
+                        if let Err(e) = keymap.upload(virtual_keyboard) {
+                            tracing::error!("unable to upload the keymap: {e:#}");
+                        }
+                    }
                     let mut guard = state.input_method_context();
                     let old = guard.replace(id);
                     if let Some(old) = old {
@@ -333,6 +521,18 @@ mod v1 {
                         guard.take();
                     }
                     context.destroy();
+                    // the next text field reports its own purpose, back to the layout of the
+                    // input method.
+                    let purpose = ContentPurpose::Normal;
+                    if state
+                        .tx
+                        .unbounded_send(
+                            Message::from(LayoutEvent::UpdateContentPurpose(purpose)).into(),
+                        )
+                        .is_err()
+                    {
+                        tracing::error!("unable to send wayland input-method-v1 event");
+                    }
                     // Hide the window. In Kwin, if the virtual keyboard button is clicked in
                     // kscreenlock, a activate signal will be sent, the window will show again.
                     state
@@ -351,6 +551,10 @@ mod v1 {
         ]);
     }
 
+    delegate_noop!(WaylandInputMethodV1Server: ignore WlSeat);
+    delegate_noop!(WaylandInputMethodV1Server: ZwpVirtualKeyboardManagerV1);
+    delegate_noop!(WaylandInputMethodV1Server: ZwpVirtualKeyboardV1);
+
     pub fn new(
         connection: &WaylandConnection,
         tx: UnboundedSender<WaylandMessage>,
@@ -365,18 +569,45 @@ mod v1 {
         let mut event_queue = connection.new_event_queue::<WaylandInputMethodV1Server>();
         let qh = event_queue.handle();
         let input_method_context = Arc::new(Mutex::new(None));
+        let text_input_serial = Arc::new(AtomicU32::new(0));
 
         global_list
             .bind::<ZwpInputMethodV1, _, _>(&qh, 1..=1, ())
             .context("failed to bind ZwpInputMethodV1")?;
+        let virtual_keyboard = match global_list
+            .bind::<ZwpVirtualKeyboardManagerV1, _, _>(&qh, 1..=1, ())
+            .context("failed to bind ZwpVirtualKeyboardManagerV1")
+            .and_then(|manager| {
+                let seat = global_list
+                    .bind::<WlSeat, _, _>(&qh, 1..=8, ())
+                    .context("failed to bind WlSeat")?;
+                Ok(manager.create_virtual_keyboard(&seat, &qh, ()))
+            }) {
+            Ok(virtual_keyboard) => Some(virtual_keyboard),
+            Err(e) => {
+                tracing::debug!(
+                    "keys which can't be committed are sent by keycodes of the compositor: {e:#}"
+                );
+                None
+            }
+        };
+        let keymap = Arc::new(Mutex::new(Keymap::default()));
 
         let client = WaylandInputMethodV1Client {
             serial: AtomicU32::new(0),
+            text_input_serial: text_input_serial.clone(),
             input_method_context: input_method_context.clone(),
+            depressed: 0,
+            virtual_keyboard: virtual_keyboard.clone(),
+            keymap: keymap.clone(),
+            pressed: vec![],
         };
         let mut server = WaylandInputMethodV1Server {
             tx,
+            text_input_serial,
             input_method_context,
+            virtual_keyboard,
+            keymap,
         };
         Ok((client, async move {
             // we should not use blocking_dispatch here. because layershellev uses blocking_dispatch, if we use blocking_dispatch it will freeze the eventloop of layershellev
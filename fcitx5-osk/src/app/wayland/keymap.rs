@@ -0,0 +1,134 @@
+use std::{io::Write as _, os::fd::AsFd as _};
+
+use anyhow::{Context, Result};
+use wayland_client::protocol::wl_keyboard::KeymapFormat;
+use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1;
+use xkeysym::Keysym;
+
+/// XKB keycodes start from 8, and X11 clients can't use keycodes larger than 255.
+const MAX_KEYS: usize = 255 - 8;
+
+/// Each keysym typed gets a key of its own in the keymap, so keysyms which aren't in the
+/// layout of the compositor, e.g. emoji, can be typed too.
+#[derive(Debug, Default)]
+pub struct Keymap {
+    keysyms: Vec<Keysym>,
+    /// When each key is used last time, the least recently used one is replaced once the keymap
+    /// is full.
+    used: Vec<u64>,
+    clock: u64,
+}
+
+impl Keymap {
+    /// Return the evdev keycode of a keysym if it is in the keymap.
+    pub fn find(&self, keysym: Keysym) -> Option<u32> {
+        self.keysyms
+            .iter()
+            .position(|k| *k == keysym)
+            .map(|i| i as u32 + 1)
+    }
+
+    /// Return the evdev keycode of a keysym, and whether the keymap is changed. Keys in
+    /// `pressed` are kept, so they can be released by the same keycodes.
+    pub fn keycode(&mut self, keysym: Keysym, pressed: &[u32]) -> (u32, bool) {
+        self.clock += 1;
+        if let Some(keycode) = self.find(keysym) {
+            self.used[keycode as usize - 1] = self.clock;
+            return (keycode, false);
+        }
+        if self.keysyms.len() < MAX_KEYS {
+            self.keysyms.push(keysym);
+            self.used.push(self.clock);
+            return (self.keysyms.len() as u32, true);
+        }
+        let i = self
+            .used
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !pressed.contains(&(*i as u32 + 1)))
+            .min_by_key(|(_, used)| **used)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| {
+                tracing::warn!("all keys of the keymap are pressed, replace one of them");
+                0
+            });
+        tracing::debug!(
+            "keymap is full, replace {:?} by {:?}",
+            self.keysyms[i],
+            keysym
+        );
+        self.keysyms[i] = keysym;
+        self.used[i] = self.clock;
+        (i as u32 + 1, true)
+    }
+
+    fn to_xkb(&self) -> String {
+        // the xkb keycode is the evdev keycode plus 8.
+        let keycodes: String = (1..=self.keysyms.len())
+            .map(|i| format!("        <K{i}> = {};\n", i + 8))
+            .collect();
+        let symbols: String = self
+            .keysyms
+            .iter()
+            .enumerate()
+            .map(|(i, keysym)| {
+                format!(
+                    "        key <K{}> {{ [ {:#x} ] }};\n",
+                    i + 1,
+                    u32::from(*keysym)
+                )
+            })
+            .collect();
+        format!(
+            "xkb_keymap {{\n    xkb_keycodes \"fcitx5-osk\" {{\n        minimum = 8;\n        maximum = 255;\n{keycodes}    }};\n    xkb_types \"fcitx5-osk\" {{ include \"complete\" }};\n    xkb_compatibility \"fcitx5-osk\" {{ include \"complete\" }};\n    xkb_symbols \"fcitx5-osk\" {{\n{symbols}    }};\n}};\n"
+        )
+    }
+
+    /// Send the keymap to the compositor, it must be done before any key is sent.
+    pub fn upload(&self, virtual_keyboard: &ZwpVirtualKeyboardV1) -> Result<()> {
+        let keymap = self.to_xkb();
+        let mut file = tempfile::tempfile().context("failed to create keymap file")?;
+        // the keymap is null-terminated.
+        file.write_all(keymap.as_bytes())?;
+        file.write_all(&[0])?;
+        virtual_keyboard.keymap(
+            KeymapFormat::XkbV1.into(),
+            file.as_fd(),
+            keymap.len() as u32 + 1,
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use xkeysym::Keysym;
+
+    use super::{Keymap, MAX_KEYS};
+
+    fn fill(keymap: &mut Keymap) {
+        for i in 0..MAX_KEYS as u32 {
+            keymap.keycode(Keysym::from(0x1000000 + i), &[]);
+        }
+    }
+
+    #[test]
+    fn full_keymap_replaces_the_least_recently_used_key() {
+        let mut keymap = Keymap::default();
+        fill(&mut keymap);
+        // the first key is used again, the second one is the least recently used now.
+        assert_eq!(keymap.keycode(Keysym::from(0x1000000), &[]), (1, false));
+        assert_eq!(keymap.keycode(Keysym::a, &[]), (2, true));
+        assert_eq!(keymap.find(Keysym::from(0x1000000)), Some(1));
+        assert_eq!(keymap.find(Keysym::from(0x1000001)), None);
+    }
+
+    #[test]
+    fn full_keymap_keeps_pressed_keys() {
+        let mut keymap = Keymap::default();
+        fill(&mut keymap);
+        assert_eq!(keymap.keycode(Keysym::a, &[1, 2]), (3, true));
+        assert_eq!(keymap.find(Keysym::from(0x1000000)), Some(1));
+        assert_eq!(keymap.find(Keysym::from(0x1000001)), Some(2));
+    }
+}
@@ -0,0 +1,311 @@
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use anyhow::Result;
+use tokio::task::JoinHandle;
+use v1::Fcitx5ControllerServiceStub;
+
+use crate::dbus::client::Fcitx5Services;
+
+use super::connection::WaylandConnection;
+
+#[derive(Default)]
+struct State {
+    fcitx5_services: Option<Fcitx5Services>,
+    bg_handle: Option<JoinHandle<()>>,
+    closed: bool,
+}
+
+/// Type keys by `zwp_virtual_keyboard_v1`, it works without fcitx5.
+#[derive(Clone)]
+pub struct VirtualKeyboardContext {
+    connection: WaylandConnection,
+    state: Arc<Mutex<State>>,
+}
+
+impl VirtualKeyboardContext {
+    pub fn new(connection: WaylandConnection) -> Self {
+        Self {
+            connection,
+            state: Default::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .expect("virtual keyboard context state is poisoned")
+    }
+
+    pub fn fcitx5_services(&self) -> Result<Fcitx5Services> {
+        let mut guard = self.state();
+        if let Some(fcitx5_services) = &guard.fcitx5_services {
+            Ok(fcitx5_services.clone())
+        } else {
+            // create a mock fcitx5 backend using virtual-keyboard-v1, so that the keyboard works
+            // in compositors without fcitx5.
+            let (client, bg) = v1::new(&self.connection)?;
+            let handle = tokio::spawn(async move {
+                if let Err(e) = bg.await {
+                    tracing::error!(
+                        "wayland virtual-keyboard-v1 event queue exit with error: {:?}",
+                        e
+                    );
+                }
+            });
+            guard.bg_handle = Some(handle);
+            let stub = Arc::new(Fcitx5ControllerServiceStub);
+            let client = Arc::new(iced::futures::lock::Mutex::new(client));
+            let fcitx5_services = Fcitx5Services::new_with(stub.clone(), stub, client);
+            guard.fcitx5_services = Some(fcitx5_services.clone());
+            Ok(fcitx5_services)
+        }
+    }
+
+    pub fn close(&self) {
+        let Some(mut guard) = self.state.lock().ok() else {
+            tracing::debug!("closing VirtualKeyboardContext, but lock is poisoned");
+            return;
+        };
+        if guard.closed {
+            return;
+        }
+        tracing::debug!("close VirtualKeyboardContext");
+        if let Some(bg_handle) = guard.bg_handle.take() {
+            bg_handle.abort();
+        }
+        drop(guard.fcitx5_services.take());
+        guard.closed = true;
+    }
+}
+
+impl Drop for VirtualKeyboardContext {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+mod v1 {
+    use std::future::Future;
+
+    use anyhow::{Context, Result};
+    use wayland_client::{
+        delegate_noop,
+        protocol::{wl_keyboard::KeyState, wl_seat::WlSeat},
+        Connection,
+    };
+    use wayland_protocols_misc::zwp_virtual_keyboard_v1::client::{
+        zwp_virtual_keyboard_manager_v1::ZwpVirtualKeyboardManagerV1,
+        zwp_virtual_keyboard_v1::ZwpVirtualKeyboardV1,
+    };
+    use xkeysym::Keysym;
+    use zbus::{Error as ZbusError, Result as ZbusResult};
+
+    use crate::{
+        app::{
+            event_keysym,
+            wayland::{connection::WaylandConnection, keymap::Keymap, modifier_mask},
+        },
+        dbus::client::{
+            IFcitx5ControllerService, IFcitx5VirtualKeyboardBackendService,
+            IFcitx5VirtualKeyboardService, InputMethodGroupInfo, InputMethodInfo,
+        },
+    };
+
+    #[derive(Debug)]
+    pub struct Fcitx5ControllerServiceStub;
+
+    impl Fcitx5ControllerServiceStub {
+        const IM_NAME: &str = "wayland-virtual-keyboard-v1";
+    }
+
+    #[async_trait::async_trait]
+    impl IFcitx5ControllerService for Fcitx5ControllerServiceStub {
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        async fn full_input_method_group_info(
+            &self,
+            _name: &str,
+        ) -> ZbusResult<InputMethodGroupInfo> {
+            let input_methods = vec![InputMethodInfo::new(Self::IM_NAME)];
+            InputMethodGroupInfo::new("", 0, "", input_methods)
+                .map_err(|e| ZbusError::Failure(e.to_string()))
+        }
+
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        async fn current_input_method(&self) -> ZbusResult<String> {
+            Ok(Self::IM_NAME.to_string())
+        }
+
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        async fn set_current_im(&self, _im: &str) -> ZbusResult<()> {
+            Ok(())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IFcitx5VirtualKeyboardService for Fcitx5ControllerServiceStub {
+        async fn show_virtual_keyboard(&self) -> ZbusResult<()> {
+            Ok(())
+        }
+
+        async fn hide_virtual_keyboard(&self) -> ZbusResult<()> {
+            Ok(())
+        }
+    }
+
+    /// WaylandVirtualKeyboardV1Client implements IFcitx5VirtualKeyboardBackendService only, the
+    /// keycodes from the keyboard are ignored, keys are typed by keysyms.
+    #[derive(Debug)]
+    pub struct WaylandVirtualKeyboardV1Client {
+        connection: Connection,
+        virtual_keyboard: ZwpVirtualKeyboardV1,
+        keymap: Keymap,
+        /// The mask of modifier keys which are pressed.
+        depressed: u32,
+        /// The evdev keycodes of keys which are pressed.
+        pressed: Vec<u32>,
+    }
+
+    impl WaylandVirtualKeyboardV1Client {
+        fn upload_keymap(&self) -> Result<()> {
+            self.keymap.upload(&self.virtual_keyboard)
+        }
+
+        fn update_modifiers(&self, depressed: u32) {
+            self.virtual_keyboard.modifiers(depressed, 0, 0, 0);
+        }
+
+        fn flush(&self) -> ZbusResult<()> {
+            self.connection
+                .flush()
+                .map_err(|e| ZbusError::Failure(format!("failed to flush wayland events: {e}")))
+        }
+    }
+
+    impl Drop for WaylandVirtualKeyboardV1Client {
+        fn drop(&mut self) {
+            tracing::debug!("destroy zwp_virtual_keyboard_v1 during drop");
+            self.virtual_keyboard.destroy();
+            let _ = self.connection.flush();
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl IFcitx5VirtualKeyboardBackendService for WaylandVirtualKeyboardV1Client {
+        #[tracing::instrument(level = "debug", skip(self), err, ret)]
+        async fn process_key_event(
+            &mut self,
+            keyval: u32,
+            keycode: u32,
+            state: u32,
+            is_release: bool,
+            time: u32,
+        ) -> ZbusResult<()> {
+            let keysym = event_keysym(keyval, keycode);
+            if keysym == Keysym::NoSymbol {
+                tracing::debug!("ignore key event without keysym, keycode: {keycode}");
+                return Ok(());
+            }
+            if let Some(mask) = modifier_mask(keysym) {
+                if is_release {
+                    self.depressed &= !mask;
+                } else {
+                    self.depressed |= mask;
+                }
+                self.update_modifiers(self.depressed);
+                return self.flush();
+            }
+
+            let (keycode, changed) = self.keymap.keycode(keysym, &self.pressed);
+            if changed {
+                self.upload_keymap()
+                    .map_err(|e| ZbusError::Failure(format!("{e:#}")))?;
+            }
+            // modifiers of a key without a keycode are applied to this key only.
+            let modifiers = state & !self.depressed;
+            if modifiers != 0 {
+                self.update_modifiers(self.depressed | modifiers);
+            }
+            let key_state = if is_release {
+                self.pressed.retain(|k| *k != keycode);
+                KeyState::Released
+            } else {
+                self.pressed.push(keycode);
+                KeyState::Pressed
+            };
+            self.virtual_keyboard.key(time, keycode, key_state.into());
+            if modifiers != 0 {
+                self.update_modifiers(self.depressed);
+            }
+            self.flush()
+        }
+
+        async fn select_candidate(&self, _index: i32) -> ZbusResult<()> {
+            Ok(())
+        }
+
+        async fn prev_page(&self, _index: i32) -> ZbusResult<()> {
+            Ok(())
+        }
+
+        async fn next_page(&self, _index: i32) -> ZbusResult<()> {
+            Ok(())
+        }
+
+        async fn reset_pressed_key_events(&mut self) -> ZbusResult<()> {
+            for keycode in self.pressed.drain(..) {
+                self.virtual_keyboard
+                    .key(0, keycode, KeyState::Released.into());
+            }
+            self.depressed = 0;
+            self.update_modifiers(0);
+            self.flush()
+        }
+    }
+
+    struct WaylandVirtualKeyboardV1Server;
+
+    delegate_noop!(WaylandVirtualKeyboardV1Server: ignore WlSeat);
+    delegate_noop!(WaylandVirtualKeyboardV1Server: ZwpVirtualKeyboardManagerV1);
+    delegate_noop!(WaylandVirtualKeyboardV1Server: ZwpVirtualKeyboardV1);
+
+    pub fn new(
+        connection: &WaylandConnection,
+    ) -> Result<(
+        WaylandVirtualKeyboardV1Client,
+        impl Future<Output = Result<()>> + 'static,
+    )> {
+        let state = connection.state()?;
+        let connection = state.connection();
+        let global_list = state.global_list()?;
+
+        let mut event_queue = connection.new_event_queue::<WaylandVirtualKeyboardV1Server>();
+        let qh = event_queue.handle();
+
+        let seat = global_list
+            .bind::<WlSeat, _, _>(&qh, 1..=8, ())
+            .context("failed to bind WlSeat")?;
+        let manager = global_list
+            .bind::<ZwpVirtualKeyboardManagerV1, _, _>(&qh, 1..=1, ())
+            .context("failed to bind ZwpVirtualKeyboardManagerV1")?;
+        let virtual_keyboard = manager.create_virtual_keyboard(&seat, &qh, ());
+
+        let client = WaylandVirtualKeyboardV1Client {
+            connection: connection.clone(),
+            virtual_keyboard,
+            keymap: Keymap::default(),
+            depressed: 0,
+            pressed: vec![],
+        };
+        // a keymap must be uploaded before any key is sent.
+        client.upload_keymap()?;
+        client.flush()?;
+        let mut server = WaylandVirtualKeyboardV1Server;
+        Ok((client, async move {
+            // we should not use blocking_dispatch here. because layershellev uses blocking_dispatch, if we use blocking_dispatch it will freeze the eventloop of layershellev
+            loop {
+                std::future::poll_fn(|cx| event_queue.poll_dispatch_pending(cx, &mut server))
+                    .await?;
+            }
+        }))
+    }
+}
@@ -4,18 +4,24 @@ use iced::{futures::stream, window::Id, Element, Subscription, Task, Theme};
 use x11rb::rust_connection::RustConnection;
 
 pub use crate::app::x11::output::{OutputContext, OutputGeometry};
+use crate::app::x11::{clipboard::ClipboardContext, xtest::XtestContext};
 use crate::{
     app::{self, Keyboard, Message},
-    config::ConfigManager,
+    config::{Backend, ConfigManager},
     window::x11::X11WindowManager,
 };
 
 use super::AsyncAppState;
 
+mod clipboard;
 mod output;
+mod xtest;
 
 pub struct X11Keyboard {
     output_context: OutputContext,
+    clipboard_context: ClipboardContext,
+    /// It is used if the backend is `Xtest`.
+    xtest_context: Option<XtestContext>,
     shutdown_flag: ShutdownFlag,
     inner: Keyboard<X11WindowManager>,
 }
@@ -24,6 +30,8 @@ impl X11Keyboard {
     pub fn new(
         config_manager: ConfigManager,
         output_context: OutputContext,
+        clipboard_context: ClipboardContext,
+        xtest_context: Option<XtestContext>,
         wait_for_socket: bool,
         modifier_workaround: bool,
         shutdown_flag: ShutdownFlag,
@@ -37,7 +45,7 @@ impl X11Keyboard {
             let shutdown_flag = shutdown_flag.clone();
             async move {
                 let res = AsyncAppState::new(
-                    config_manager.as_ref(),
+                    &config_manager,
                     wait_for_socket,
                     modifier_workaround,
                     shutdown_flag,
@@ -62,6 +70,8 @@ impl X11Keyboard {
         (
             Self {
                 output_context,
+                clipboard_context,
+                xtest_context,
                 shutdown_flag,
                 inner,
             },
@@ -79,6 +89,7 @@ impl X11Keyboard {
         let mut subscriptions = vec![
             self.inner.subscription(),
             self.output_context.subscription(),
+            self.clipboard_context.subscription(),
         ];
 
         // These messages only work in the first call
@@ -90,6 +101,21 @@ impl X11Keyboard {
                 "Unable to listen to the changes of x11 output",
             ));
         }
+        if let Err(e) = self.clipboard_context.listen() {
+            tracing::warn!("Unable to listen to the changes of x11 clipboard: {e:?}");
+        }
+        if let Some(xtest_context) = &self.xtest_context {
+            match xtest_context.fcitx5_services() {
+                // switch to our XTEST implementation.
+                Ok(fcitx5_services) => {
+                    once_messages.push(Message::UpdateFcitx5Services(fcitx5_services))
+                }
+                Err(e) => once_messages.push(app::fatal_with_context(
+                    e,
+                    "failed to create Fcitx5Services for XTEST",
+                )),
+            }
+        }
         subscriptions.push(Subscription::run_with_id(
             "external::wayland_once",
             stream::iter(once_messages),
@@ -101,6 +127,10 @@ impl X11Keyboard {
     pub fn update(&mut self, message: Message) -> Task<Message> {
         if self.shutdown_flag.get() {
             self.output_context.close();
+            self.clipboard_context.close();
+            if let Some(xtest_context) = &self.xtest_context {
+                xtest_context.close();
+            }
         }
 
         self.inner.update(message)
@@ -120,6 +150,15 @@ pub fn start(
 ) -> Result<()> {
     // each eventloop should has its own connection.
     let output_context = OutputContext::new(xcb_connection)?;
+    let clipboard_context = ClipboardContext::new(xcb_connection);
+    let xtest_context = match config_manager.as_ref().backend() {
+        Backend::Xtest => Some(XtestContext::new(xcb_connection)),
+        Backend::Fcitx5 => None,
+        backend => {
+            tracing::warn!("backend {backend} isn't supported in X11, fcitx5 is used");
+            None
+        }
+    };
 
     iced::daemon(clap::crate_name!(), X11Keyboard::update, X11Keyboard::view)
         .theme(X11Keyboard::theme)
@@ -128,6 +167,8 @@ pub fn start(
             let (keyboard, task) = X11Keyboard::new(
                 config_manager,
                 output_context,
+                clipboard_context,
+                xtest_context,
                 wait_for_socket,
                 modifier_workaround,
                 shutdown_flag,
@@ -0,0 +1,309 @@
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::Result;
+use iced::{
+    futures::{
+        channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        stream,
+    },
+    Subscription,
+};
+use x11rb::{
+    connection::Connection as _,
+    protocol::{
+        xfixes::{self, ConnectionExt as _, SelectionEventMask},
+        xproto::{self, Atom, AtomEnum, ConnectionExt as _, CreateWindowAux, Window, WindowClass},
+        Event as X11Event,
+    },
+    rust_connection::RustConnection,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT, NONE,
+};
+
+use crate::{app::Message, state::ClipboardEvent};
+
+x11rb::atom_manager! {
+    /// Atoms used by the clipboard.
+    pub Atoms:
+    /// A handle to a response from the X11 server.
+    AtomsCookie {
+        CLIPBOARD,
+        TARGETS,
+        UTF8_STRING,
+        INCR,
+        FCITX5_OSK_CLIPBOARD,
+        X_KDE_PASSWORD_MANAGER_HINT: b"x-kde-passwordManagerHint",
+    }
+}
+
+/// Texts larger than this are not recorded.
+const MAX_TEXT_LEN: u32 = 64 * 1024;
+
+struct State {
+    rx: Option<UnboundedReceiver<Message>>,
+    bg_handle: Option<JoinHandle<()>>,
+    closed: bool,
+}
+
+impl State {
+    fn close(&mut self) {
+        if self.closed {
+            return;
+        }
+        tracing::debug!("Close ClipboardContext State");
+        if let Some(bg_handle) = self.bg_handle.take() {
+            // Wake the thread
+            bg_handle.thread().unpark();
+        }
+        self.closed = true;
+    }
+}
+
+impl Drop for State {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Watch the CLIPBOARD selection by the XFIXES extension.
+#[derive(Clone)]
+pub struct ClipboardContext {
+    connection_supplier: Arc<dyn Fn() -> Result<(RustConnection, usize)> + Send + Sync>,
+    tx: UnboundedSender<Message>,
+    state: Arc<Mutex<State>>,
+}
+
+impl ClipboardContext {
+    pub fn new<F>(connection_supplier: F) -> Self
+    where
+        F: Fn() -> Result<(RustConnection, usize)> + 'static + Send + Sync,
+    {
+        let (tx, rx) = mpsc::unbounded();
+        Self {
+            connection_supplier: Arc::new(connection_supplier),
+            tx,
+            state: Arc::new(Mutex::new(State {
+                rx: Some(rx),
+                bg_handle: None,
+                closed: false,
+            })),
+        }
+    }
+
+    fn state(&self) -> Option<MutexGuard<'_, State>> {
+        self.state.lock().ok()
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        const EXTERNAL_SUBSCRIPTION_ID: &str = "external::x11_clipboard";
+        if let Some(rx) = self.state().and_then(|mut s| s.rx.take()) {
+            Subscription::run_with_id(EXTERNAL_SUBSCRIPTION_ID, rx)
+        } else {
+            // should always return a subscription with the same id, otherwise, the first one will
+            // be dropped.
+            Subscription::run_with_id(EXTERNAL_SUBSCRIPTION_ID, stream::empty())
+        }
+    }
+
+    pub fn listen(&self) -> Result<()> {
+        let Some(mut guard) = self.state() else {
+            anyhow::bail!("Unable to listen, the state of ClipboardContext is poisoned");
+        };
+        if guard.closed {
+            return Ok(());
+        }
+        if guard.bg_handle.is_none() {
+            let bg = listen(self).inspect_err(|_| {
+                // don't try again, the extension may not be supported.
+                guard.closed = true;
+            })?;
+            guard.bg_handle = Some(thread::spawn(move || {
+                if let Err(e) = bg() {
+                    tracing::error!("x11 clipboard eventloop exit with error: {:?}", e);
+                }
+            }));
+        }
+        Ok(())
+    }
+
+    pub fn close(&mut self) {
+        let Some(mut guard) = self.state() else {
+            tracing::debug!("Closing ClipboardContext, but lock is poisoned");
+            return;
+        };
+        guard.close();
+    }
+
+    fn send_copied_event(&self, text: String) {
+        if self
+            .tx
+            .unbounded_send(Message::from(ClipboardEvent::Copied(text)))
+            .is_err()
+        {
+            tracing::error!("Unable to send ClipboardEvent::Copied event");
+        }
+    }
+}
+
+fn listen(clipboard_context: &ClipboardContext) -> Result<impl FnOnce() -> Result<()>> {
+    let (conn, default_screen) = (clipboard_context.connection_supplier)()?;
+
+    let atoms = Atoms::new(&conn)?.reply()?;
+
+    let setup = conn.setup();
+    let Some(screen) = setup.roots.get(default_screen) else {
+        anyhow::bail!(
+            "Unable to get the default screen[{}], size: {}",
+            default_screen,
+            setup.roots_len()
+        );
+    };
+
+    // Make sure xfixes extension exits and has the minimum version
+    conn.xfixes_query_version(1, 0)?.reply()?;
+
+    // The converted selection is stored in a property of this window.
+    let window = conn.generate_id()?;
+    conn.create_window(
+        COPY_DEPTH_FROM_PARENT,
+        window,
+        screen.root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_ONLY,
+        COPY_FROM_PARENT,
+        &CreateWindowAux::new(),
+    )?;
+
+    conn.xfixes_select_selection_input(
+        window,
+        atoms.CLIPBOARD,
+        SelectionEventMask::SET_SELECTION_OWNER,
+    )?;
+
+    conn.flush()?;
+
+    let clipboard_context = clipboard_context.clone();
+    let bg = move || {
+        loop {
+            if clipboard_context.state().filter(|g| !g.closed).is_none() {
+                break;
+            }
+
+            let Some(event) = conn.poll_for_event()? else {
+                // wait until timeout or unpark
+                thread::park_timeout(Duration::from_millis(50));
+                continue;
+            };
+
+            match event {
+                X11Event::XfixesSelectionNotify(xfixes::SelectionNotifyEvent {
+                    owner,
+                    selection_timestamp,
+                    ..
+                }) => {
+                    if owner == NONE || owner == window {
+                        continue;
+                    }
+                    // check the targets first, secrets shouldn't be recorded.
+                    conn.convert_selection(
+                        window,
+                        atoms.CLIPBOARD,
+                        atoms.TARGETS,
+                        atoms.FCITX5_OSK_CLIPBOARD,
+                        selection_timestamp,
+                    )?;
+                    conn.flush()?;
+                }
+                X11Event::SelectionNotify(xproto::SelectionNotifyEvent {
+                    target,
+                    property,
+                    time,
+                    ..
+                }) => {
+                    if property == NONE {
+                        tracing::debug!("Unable to convert the clipboard to target[{target}]");
+                        continue;
+                    }
+                    if target == atoms.TARGETS {
+                        if has_text(&conn, window, &atoms)? {
+                            conn.convert_selection(
+                                window,
+                                atoms.CLIPBOARD,
+                                atoms.UTF8_STRING,
+                                atoms.FCITX5_OSK_CLIPBOARD,
+                                time,
+                            )?;
+                            conn.flush()?;
+                        }
+                    } else if target == atoms.UTF8_STRING {
+                        if let Some(text) = read_text(&conn, window, &atoms)? {
+                            clipboard_context.send_copied_event(text);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        conn.destroy_window(window)?;
+        conn.flush()?;
+        Ok(())
+    };
+    Ok(bg)
+}
+
+fn read_property(
+    conn: &RustConnection,
+    window: Window,
+    atoms: &Atoms,
+    typ: impl Into<Atom>,
+) -> Result<Option<(Atom, Vec<u8>)>> {
+    let reply = conn
+        .get_property(
+            true,
+            window,
+            atoms.FCITX5_OSK_CLIPBOARD,
+            typ,
+            0,
+            MAX_TEXT_LEN / 4 + 1,
+        )?
+        .reply()?;
+    // the content is sent by INCR if it is too large.
+    if reply.type_ == atoms.INCR || reply.bytes_after > 0 {
+        tracing::debug!("Skip a text larger than {} bytes", MAX_TEXT_LEN);
+        return Ok(None);
+    }
+    Ok(Some((reply.type_, reply.value)))
+}
+
+/// Whether the clipboard can be converted to a text, and it isn't a secret.
+fn has_text(conn: &RustConnection, window: Window, atoms: &Atoms) -> Result<bool> {
+    let Some((_, value)) = read_property(conn, window, atoms, AtomEnum::ATOM)? else {
+        return Ok(false);
+    };
+    let targets: Vec<_> = value
+        .chunks_exact(4)
+        .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+        .collect();
+    if targets.contains(&atoms.X_KDE_PASSWORD_MANAGER_HINT) {
+        tracing::debug!("Skip a secret in the clipboard");
+        return Ok(false);
+    }
+    Ok(targets.contains(&atoms.UTF8_STRING))
+}
+
+fn read_text(conn: &RustConnection, window: Window, atoms: &Atoms) -> Result<Option<String>> {
+    let Some((typ, value)) = read_property(conn, window, atoms, atoms.UTF8_STRING)? else {
+        return Ok(None);
+    };
+    if typ != atoms.UTF8_STRING {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(value).ok())
+}
@@ -0,0 +1,373 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use anyhow::{Context as _, Result};
+use x11rb::{
+    connection::{Connection as _, RequestConnection as _},
+    protocol::{
+        xproto::{self, ConnectionExt as _, Keycode, Mapping, Window},
+        xtest::{self, ConnectionExt as _},
+        Event,
+    },
+    rust_connection::RustConnection,
+    wrapper::ConnectionExt as _,
+    CURRENT_TIME, NONE,
+};
+use xkeysym::Keysym;
+use zbus::{Error as ZbusError, Result as ZbusResult};
+
+use crate::{
+    app::event_keysym,
+    dbus::client::{
+        Fcitx5Services, IFcitx5ControllerService, IFcitx5VirtualKeyboardBackendService,
+        IFcitx5VirtualKeyboardService, InputMethodGroupInfo, InputMethodInfo,
+    },
+};
+
+#[derive(Default)]
+struct State {
+    fcitx5_services: Option<Fcitx5Services>,
+    closed: bool,
+}
+
+/// Type keys by the XTEST extension, it works without fcitx5.
+#[derive(Clone)]
+pub struct XtestContext {
+    connection_supplier: Arc<dyn Fn() -> Result<(RustConnection, usize)> + Send + Sync>,
+    state: Arc<Mutex<State>>,
+}
+
+impl XtestContext {
+    pub fn new<F>(connection_supplier: F) -> Self
+    where
+        F: Fn() -> Result<(RustConnection, usize)> + 'static + Send + Sync,
+    {
+        Self {
+            connection_supplier: Arc::new(connection_supplier),
+            state: Default::default(),
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("xtest context state is poisoned")
+    }
+
+    pub fn fcitx5_services(&self) -> Result<Fcitx5Services> {
+        let mut guard = self.state();
+        if guard.closed {
+            anyhow::bail!("XtestContext is closed");
+        }
+        if let Some(fcitx5_services) = &guard.fcitx5_services {
+            return Ok(fcitx5_services.clone());
+        }
+        // create a mock fcitx5 backend using XTEST, so that the keyboard works without fcitx5.
+        let client = X11XtestClient::new(&*self.connection_supplier)?;
+        let stub = Arc::new(Fcitx5ControllerServiceStub);
+        let client = Arc::new(iced::futures::lock::Mutex::new(client));
+        let fcitx5_services = Fcitx5Services::new_with(stub.clone(), stub, client);
+        guard.fcitx5_services = Some(fcitx5_services.clone());
+        Ok(fcitx5_services)
+    }
+
+    pub fn close(&self) {
+        let Some(mut guard) = self.state.lock().ok() else {
+            tracing::debug!("closing XtestContext, but lock is poisoned");
+            return;
+        };
+        if guard.closed {
+            return;
+        }
+        tracing::debug!("close XtestContext");
+        drop(guard.fcitx5_services.take());
+        guard.closed = true;
+    }
+}
+
+#[derive(Debug)]
+struct Fcitx5ControllerServiceStub;
+
+impl Fcitx5ControllerServiceStub {
+    const IM_NAME: &str = "x11-xtest";
+}
+
+#[async_trait::async_trait]
+impl IFcitx5ControllerService for Fcitx5ControllerServiceStub {
+    #[tracing::instrument(level = "debug", skip(self), err, ret)]
+    async fn full_input_method_group_info(&self, _name: &str) -> ZbusResult<InputMethodGroupInfo> {
+        let input_methods = vec![InputMethodInfo::new(Self::IM_NAME)];
+        InputMethodGroupInfo::new("", 0, "", input_methods)
+            .map_err(|e| ZbusError::Failure(e.to_string()))
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), err, ret)]
+    async fn current_input_method(&self) -> ZbusResult<String> {
+        Ok(Self::IM_NAME.to_string())
+    }
+
+    #[tracing::instrument(level = "debug", skip(self), err, ret)]
+    async fn set_current_im(&self, _im: &str) -> ZbusResult<()> {
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl IFcitx5VirtualKeyboardService for Fcitx5ControllerServiceStub {
+    async fn show_virtual_keyboard(&self) -> ZbusResult<()> {
+        Ok(())
+    }
+
+    async fn hide_virtual_keyboard(&self) -> ZbusResult<()> {
+        Ok(())
+    }
+}
+
+/// The keysym of a modifier mask, it is pressed for a key which needs the modifier.
+const MODIFIER_KEYSYMS: [(u32, Keysym); 4] = [
+    (1, Keysym::Shift_L),
+    (1 << 2, Keysym::Control_L),
+    (1 << 3, Keysym::Alt_L),
+    (1 << 6, Keysym::Super_L),
+];
+
+const SHIFT_MASK: u32 = 1;
+
+/// A key pressed by this client, it is released by the same keycode.
+#[derive(Debug)]
+struct PressedKey {
+    keysym: u32,
+    keycode: Keycode,
+    /// Modifier keys pressed with this key, they are released after this key.
+    modifiers: Vec<Keycode>,
+}
+
+/// X11XtestClient implements IFcitx5VirtualKeyboardBackendService only, the keycodes from the
+/// keyboard are ignored, keys are looked up by keysyms in the current keymap.
+#[derive(Debug)]
+struct X11XtestClient {
+    connection: RustConnection,
+    root: Window,
+    pressed: Vec<PressedKey>,
+    /// Spare keycodes which are remapped to keysyms missing in the keymap.
+    remapped: Vec<Keycode>,
+    /// The min keycode and the keyboard mapping, it is fetched again after a MappingNotify event
+    /// or after a keycode is remapped.
+    mapping: Option<(Keycode, xproto::GetKeyboardMappingReply)>,
+}
+
+impl X11XtestClient {
+    fn new(connection_supplier: &dyn Fn() -> Result<(RustConnection, usize)>) -> Result<Self> {
+        let (connection, screen_num) = connection_supplier()?;
+        connection
+            .extension_information(xtest::X11_EXTENSION_NAME)?
+            .context("XTEST extension is not supported")?;
+        let root = connection.setup().roots[screen_num].root;
+        Ok(Self {
+            connection,
+            root,
+            pressed: vec![],
+            remapped: vec![],
+            mapping: None,
+        })
+    }
+
+    fn keyboard_mapping(&mut self) -> Result<&(Keycode, xproto::GetKeyboardMappingReply)> {
+        // MappingNotify is sent to every client, e.g. the layout is changed by setxkbmap.
+        while let Some(event) = self.connection.poll_for_event()? {
+            if let Event::MappingNotify(e) = event {
+                if e.request == Mapping::KEYBOARD {
+                    self.mapping = None;
+                }
+            }
+        }
+        let mapping = match self.mapping.take() {
+            Some(mapping) => mapping,
+            None => {
+                let setup = self.connection.setup();
+                let (min, max) = (setup.min_keycode, setup.max_keycode);
+                let mapping = self
+                    .connection
+                    .get_keyboard_mapping(min, max - min + 1)?
+                    .reply()?;
+                (min, mapping)
+            }
+        };
+        Ok(self.mapping.insert(mapping))
+    }
+
+    /// Find the keycode of a keysym and whether shift is needed. A spare keycode is remapped to
+    /// the keysym if it is missing in the keymap.
+    fn keycode(&mut self, keysym: u32) -> Result<(Keycode, bool)> {
+        let (min, mapping) = self.keyboard_mapping()?;
+        let min = *min;
+        let per = mapping.keysyms_per_keycode as usize;
+        let keysyms_of = |i: usize| &mapping.keysyms[i * per..(i + 1) * per];
+        let count = mapping.keysyms.len() / per.max(1);
+        for level in 0..per.min(2) {
+            if let Some(i) = (0..count).find(|i| keysyms_of(*i)[level] == keysym) {
+                return Ok((min + i as u8, level == 1));
+            }
+        }
+
+        let spare = (0..count)
+            .rev()
+            .find(|i| keysyms_of(*i).iter().all(|k| *k == NONE))
+            .context("there is no spare keycode to remap")?;
+        let keycode = min + spare as u8;
+        tracing::debug!("remap keycode {keycode} to keysym {keysym:#x}");
+        self.connection
+            .change_keyboard_mapping(1, keycode, per as u8, &vec![keysym; per])?;
+        // make sure the new mapping is applied before the key is pressed.
+        self.connection.sync()?;
+        self.remapped.push(keycode);
+        self.mapping = None;
+        Ok((keycode, false))
+    }
+
+    fn fake_key(&self, keycode: Keycode, is_release: bool) -> Result<()> {
+        let type_ = if is_release {
+            xproto::KEY_RELEASE_EVENT
+        } else {
+            xproto::KEY_PRESS_EVENT
+        };
+        self.connection
+            .xtest_fake_input(type_, keycode, CURRENT_TIME, self.root, 0, 0, 0)?;
+        Ok(())
+    }
+
+    fn press(&mut self, keysym: u32, state: u32) -> Result<()> {
+        let (keycode, shift) = self.keycode(keysym)?;
+        let mut modifiers = state;
+        if shift {
+            modifiers |= SHIFT_MASK;
+        }
+        // modifiers held by their own keys are not pressed again.
+        let held = self
+            .pressed
+            .iter()
+            .filter_map(|p| {
+                MODIFIER_KEYSYMS
+                    .iter()
+                    .find(|(_, k)| u32::from(*k) == p.keysym)
+            })
+            .fold(0, |held, (mask, _)| held | mask);
+        let mut modifier_keycodes = vec![];
+        for (mask, modifier_keysym) in MODIFIER_KEYSYMS {
+            if modifiers & mask != 0 && held & mask == 0 {
+                let (modifier_keycode, _) = self.keycode(modifier_keysym.into())?;
+                self.fake_key(modifier_keycode, false)?;
+                modifier_keycodes.push(modifier_keycode);
+            }
+        }
+        self.fake_key(keycode, false)?;
+        self.pressed.push(PressedKey {
+            keysym,
+            keycode,
+            modifiers: modifier_keycodes,
+        });
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn release(&mut self, keysym: u32) -> Result<()> {
+        let pressed_key = match self.pressed.iter().position(|p| p.keysym == keysym) {
+            Some(i) => self.pressed.remove(i),
+            None => PressedKey {
+                keysym,
+                keycode: self.keycode(keysym)?.0,
+                modifiers: vec![],
+            },
+        };
+        self.release_pressed_key(pressed_key)?;
+        self.connection.flush()?;
+        Ok(())
+    }
+
+    fn release_pressed_key(&mut self, pressed_key: PressedKey) -> Result<()> {
+        self.fake_key(pressed_key.keycode, true)?;
+        for modifier_keycode in pressed_key.modifiers.iter().rev() {
+            self.fake_key(*modifier_keycode, true)?;
+        }
+        if self.remapped.contains(&pressed_key.keycode)
+            && !self
+                .pressed
+                .iter()
+                .any(|p| p.keycode == pressed_key.keycode)
+        {
+            self.restore(pressed_key.keycode)?;
+        }
+        Ok(())
+    }
+
+    /// Map a remapped keycode back to nothing, so it can be used by other keysyms.
+    fn restore(&mut self, keycode: Keycode) -> Result<()> {
+        // the key event should be handled with the remapped keysym.
+        self.connection.sync()?;
+        let (_, mapping) = self.keyboard_mapping()?;
+        let per = mapping.keysyms_per_keycode;
+        self.connection
+            .change_keyboard_mapping(1, keycode, per, &vec![NONE; per as usize])?;
+        self.remapped.retain(|k| *k != keycode);
+        self.mapping = None;
+        Ok(())
+    }
+}
+
+impl Drop for X11XtestClient {
+    fn drop(&mut self) {
+        tracing::debug!("drop X11XtestClient");
+        for keycode in mem::take(&mut self.remapped) {
+            if let Err(e) = self.restore(keycode) {
+                tracing::warn!("failed to restore keycode {keycode}: {e:?}");
+            }
+        }
+        let _ = self.connection.flush();
+    }
+}
+
+#[async_trait::async_trait]
+impl IFcitx5VirtualKeyboardBackendService for X11XtestClient {
+    #[tracing::instrument(level = "debug", skip(self), err, ret)]
+    async fn process_key_event(
+        &mut self,
+        keyval: u32,
+        keycode: u32,
+        state: u32,
+        is_release: bool,
+        _time: u32,
+    ) -> ZbusResult<()> {
+        let keysym = event_keysym(keyval, keycode);
+        if keysym == Keysym::NoSymbol {
+            tracing::debug!("ignore key event without keysym, keycode: {keycode}");
+            return Ok(());
+        }
+        let res = if is_release {
+            self.release(keysym.into())
+        } else {
+            self.press(keysym.into(), state)
+        };
+        res.map_err(|e| ZbusError::Failure(format!("{e:#}")))
+    }
+
+    async fn select_candidate(&self, _index: i32) -> ZbusResult<()> {
+        Ok(())
+    }
+
+    async fn prev_page(&self, _index: i32) -> ZbusResult<()> {
+        Ok(())
+    }
+
+    async fn next_page(&self, _index: i32) -> ZbusResult<()> {
+        Ok(())
+    }
+
+    async fn reset_pressed_key_events(&mut self) -> ZbusResult<()> {
+        let mut res = Ok(());
+        for pressed_key in mem::take(&mut self.pressed).into_iter().rev() {
+            res = res.and(self.release_pressed_key(pressed_key));
+        }
+        res.and_then(|_| Ok(self.connection.flush()?))
+            .map_err(|e| ZbusError::Failure(format!("{e:#}")))
+    }
+}
@@ -50,6 +50,16 @@ pub struct Config {
     #[serde(with = "humantime_serde", default = "default_holding_timeout")]
     holding_timeout: Duration,
 
+    /// How long will a repeatable key be held before it starts repeating.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(with = "humantime_serde", default = "default_key_repeat_delay")]
+    key_repeat_delay: Duration,
+
+    /// The interval between two repeated key events.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(with = "humantime_serde", default = "default_key_repeat_interval")]
+    key_repeat_interval: Duration,
+
     #[getset(get = "pub", set = "pub")]
     #[serde(default = "default_theme")]
     theme: String,
@@ -78,6 +88,12 @@ pub struct Config {
     #[serde(default)]
     im_layout_mapping: HashMap<String, HashMap<String, String>>,
 
+    /// Layouts of the purposes of text fields, they take precedence over `im_layout_mapping`.
+    /// The purposes are only reported by the input method protocol of Wayland.
+    #[getset(get = "pub", set = "pub")]
+    #[serde(default)]
+    content_purpose_layout_mapping: HashMap<String, HashMap<ContentPurpose, String>>,
+
     #[getset(get = "pub", set = "pub")]
     #[serde(default)]
     im_font_mapping: HashMap<String, String>,
@@ -99,6 +115,11 @@ pub struct Config {
     #[serde(default = "default_modifier_workaround_keycodes")]
     modifier_workaround_keycodes: Vec<u16>,
 
+    /// How keys are typed, it takes effect after restarting.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    backend: Backend,
+
     /// How long will the keyboard wait after receiving a signal from fcitx5.
     #[getset(get = "pub", set = "pub")]
     #[serde(with = "humantime_serde", default = "default_hide_delay")]
@@ -118,6 +139,90 @@ pub struct Config {
     #[getset(get = "pub", set = "pub")]
     #[serde(default)]
     default_portrait_layout: String,
+
+    /// Type a word by swiping across the keys instead of tapping each of them.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    gesture_typing: bool,
+
+    /// Tap Shift, Ctrl, Alt or Super once to apply it to the next key, tap it twice to lock it.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    sticky_modifiers: bool,
+
+    /// A sticky modifier tapped twice within this duration will be locked.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(with = "humantime_serde", default = "default_double_tap_timeout")]
+    double_tap_timeout: Duration,
+
+    /// Suggest words for the typed prefix when the input method is a keyboard layout.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    word_prediction: bool,
+
+    /// The word list for prediction, default to `$XDG_CONFIG_HOME/fcitx5-osk/words.txt`.
+    #[serde(default)]
+    word_list_path: Option<PathBuf>,
+
+    /// Learn typed words, they are never learned in password fields or on the lock screen.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default = "default_learn_words")]
+    learn_words: bool,
+
+    /// Words learned from the user, default to `$XDG_CONFIG_HOME/fcitx5-osk/user_words.txt`.
+    #[serde(default)]
+    user_dictionary_path: Option<PathBuf>,
+
+    /// Record the text copied to the clipboard, it can be pasted from the clipboard view.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    clipboard_history: bool,
+
+    /// The max number of unpinned entries in the clipboard history.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default = "default_clipboard_history_size")]
+    clipboard_history_size: usize,
+
+    /// Record the clipboard on the lock screen too.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    clipboard_history_on_lock_screen: bool,
+
+    /// Show an enlarged preview above the pressed key, it follows the finger sliding to another
+    /// key.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default = "default_key_preview")]
+    key_preview: bool,
+
+    /// Show the key preview on the lock screen too.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    key_preview_on_lock_screen: bool,
+
+    /// Drag on the space key (or keys marked by `tp = true`) to move the text cursor.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    cursor_trackpad: bool,
+
+    /// Send Up and Down too when dragging vertically on the trackpad.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    cursor_trackpad_vertical: bool,
+
+    /// Swipe down over the keys to close the keyboard.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    swipe_down_to_close: bool,
+
+    /// Swipe left from the Backspace key to delete a word by Ctrl+BackSpace.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    swipe_left_to_delete_word: bool,
+
+    /// Swipe left or right with two fingers to select the next or the previous input method.
+    #[getset(get_copy = "pub", set = "pub")]
+    #[serde(default)]
+    two_finger_swipe_to_switch_im: bool,
 }
 
 impl Config {
@@ -144,6 +249,14 @@ impl Config {
     pub fn set_preferred_output_name(&mut self, name: String) {
         self.preferred_output_name = Some(name);
     }
+
+    pub fn word_list_path(&self) -> Option<&PathBuf> {
+        self.word_list_path.as_ref()
+    }
+
+    pub fn user_dictionary_path(&self) -> Option<&PathBuf> {
+        self.user_dictionary_path.as_ref()
+    }
 }
 
 fn default_landscape_width() -> u16 {
@@ -162,6 +275,30 @@ fn default_holding_timeout() -> Duration {
     Duration::from_millis(200)
 }
 
+fn default_key_repeat_delay() -> Duration {
+    Duration::from_millis(500)
+}
+
+fn default_key_repeat_interval() -> Duration {
+    Duration::from_millis(50)
+}
+
+fn default_double_tap_timeout() -> Duration {
+    Duration::from_millis(300)
+}
+
+fn default_clipboard_history_size() -> usize {
+    20
+}
+
+fn default_key_preview() -> bool {
+    true
+}
+
+fn default_learn_words() -> bool {
+    true
+}
+
 fn default_hide_delay() -> Duration {
     Duration::from_millis(1000)
 }
@@ -182,7 +319,7 @@ fn default_modifier_workaround_keycodes() -> Vec<u16> {
 }
 
 pub struct ConfigManager {
-    _path: Option<PathBuf>,
+    path: Option<PathBuf>,
     config: Config,
     writer: UnboundedSender<String>,
 }
@@ -197,7 +334,7 @@ impl ConfigManager {
         };
         let (tx, mut rx) = mpsc::unbounded();
         let res = Self {
-            _path: path.cloned(),
+            path: path.cloned(),
             config,
             writer: tx,
         };
@@ -237,6 +374,25 @@ impl ConfigManager {
         Ok((res, bg))
     }
 
+    pub fn path(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+
+    /// Read the config file again, return false if the config isn't changed.
+    pub fn reload(&mut self) -> Result<bool> {
+        let config: Config = match &self.path {
+            Some(path) if path.exists() => Figment::new().merge(Toml::file(path)).extract()?,
+            _ => return Ok(false),
+        };
+        // The config file is written by us too, compare the serialized content to skip our own
+        // changes.
+        if toml::to_string(&config)? == toml::to_string(&self.config)? {
+            return Ok(false);
+        }
+        self.config = config;
+        Ok(true)
+    }
+
     pub fn try_write(&mut self) -> bool {
         let content = match toml::to_string(&self.config) {
             Ok(c) => c,
@@ -284,3 +440,38 @@ pub enum IndicatorDisplay {
     AlwaysOn,
     AlwaysOff,
 }
+
+/// The backend typing the keys.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+pub enum Backend {
+    /// Keys are sent to fcitx5 via dbus.
+    #[default]
+    Fcitx5,
+    /// Keys are typed by `zwp_virtual_keyboard_v1` of Wayland, fcitx5 isn't needed.
+    VirtualKeyboard,
+    /// Keys are typed by the XTEST extension of X11, fcitx5 isn't needed.
+    Xtest,
+}
+
+/// The purpose of the focused text field, e.g. a PIN field is `Digits`.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash, strum::Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ContentPurpose {
+    #[default]
+    Normal,
+    Alpha,
+    Digits,
+    Number,
+    Phone,
+    Url,
+    Email,
+    Name,
+    Password,
+    Date,
+    Time,
+    Datetime,
+    Terminal,
+}
@@ -11,7 +11,7 @@ use fcitx5_osk_common::dbus::client::Fcitx5OskKeyHelperControllerServiceProxy;
 use getset::Getters;
 use iced::futures::lock::Mutex as IcedFuturesMutex;
 use serde::Deserialize;
-use tokio::time;
+use tokio::{task::JoinHandle, time};
 use zbus::{fdo, zvariant::OwnedValue, Connection, Result as ZbusResult};
 use zvariant::Type;
 
@@ -272,10 +272,54 @@ struct FusedFcitx5VirtualKeyboardBackendService<'a> {
     key_helper_serial: Option<u64>,
     key_helper_created: bool,
     /// Avoid created key helper in the login screen
-    fcitx5_osk_key_helper_controller: MaybeUninit<Fcitx5OskKeyHelperControllerServiceProxy<'a>>,
+    fcitx5_osk_key_helper_controller:
+        MaybeUninit<Fcitx5OskKeyHelperControllerServiceProxy<'static>>,
+    /// Keycodes pressed by the key helper.
+    key_helper_pressed: Vec<u16>,
+    /// Renew the lease of the key helper while keys are pressed, otherwise they are released by
+    /// the key helper.
+    key_helper_lease_handle: Option<JoinHandle<()>>,
     modifier_workaround_keycodes: Vec<u16>,
 }
 
+impl FusedFcitx5VirtualKeyboardBackendService<'_> {
+    fn update_key_helper_lease(&mut self, keycode: u16, is_release: bool) {
+        if is_release {
+            self.key_helper_pressed.retain(|k| *k != keycode);
+        } else {
+            self.key_helper_pressed.push(keycode);
+        }
+        if self.key_helper_pressed.is_empty() {
+            self.stop_key_helper_lease();
+        } else if self.key_helper_lease_handle.is_none() && self.key_helper_created {
+            // SAFETY `self.fcitx5_osk_key_helper_controller` will be initialized if
+            // `self.key_helper_created` is true
+            let fcitx5_osk_key_helper_controller =
+                unsafe { self.fcitx5_osk_key_helper_controller.assume_init_ref() }.clone();
+            self.key_helper_lease_handle = Some(tokio::spawn(async move {
+                loop {
+                    match fcitx5_osk_key_helper_controller.renew_lease().await {
+                        Ok(max_hold_time) => {
+                            time::sleep(Duration::from_millis(max_hold_time) / 2).await
+                        }
+                        Err(e) => {
+                            tracing::warn!("Unable to renew the lease of key helper: {e:?}");
+                            break;
+                        }
+                    }
+                }
+            }));
+        }
+    }
+
+    fn stop_key_helper_lease(&mut self) {
+        self.key_helper_pressed.clear();
+        if let Some(handle) = self.key_helper_lease_handle.take() {
+            handle.abort();
+        }
+    }
+}
+
 impl Debug for FusedFcitx5VirtualKeyboardBackendService<'_> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
         f.write_fmt(
@@ -290,6 +334,7 @@ impl Debug for FusedFcitx5VirtualKeyboardBackendService<'_> {
 
 impl Drop for FusedFcitx5VirtualKeyboardBackendService<'_> {
     fn drop(&mut self) {
+        self.stop_key_helper_lease();
         if self.key_helper_created {
             // SAFETY `self.key_helper_created` will be true only if
             // fcitx5_osk_key_helper_controller is inited
@@ -322,6 +367,10 @@ impl IFcitx5VirtualKeyboardBackendService for FusedFcitx5VirtualKeyboardBackendS
                     .write(fcitx5_osk_key_helper_controller);
                 self.key_helper_created = true;
             }
+            if self.key_helper_serial.is_none() {
+                // Keys will be released after serial is reset
+                self.stop_key_helper_lease();
+            }
             // SAFETY `self.fcitx5_osk_key_helper_controller` will be initialized if not
             let fcitx5_osk_key_helper_controller =
                 unsafe { self.fcitx5_osk_key_helper_controller.assume_init_ref() };
@@ -340,6 +389,7 @@ impl IFcitx5VirtualKeyboardBackendService for FusedFcitx5VirtualKeyboardBackendS
                     .process_key_event(key_helper_serial, code, is_release)
                     .await?,
             );
+            self.update_key_helper_lease(code, is_release);
 
             // TODO sleep is not good enough, but i don't want more complicated method
             // Sleep a while to let fcitx5 send hide event.
@@ -394,6 +444,7 @@ impl IFcitx5VirtualKeyboardBackendService for FusedFcitx5VirtualKeyboardBackendS
     }
 
     async fn reset_pressed_key_events(&mut self) -> ZbusResult<()> {
+        self.stop_key_helper_lease();
         if self.key_helper_created {
             // SAFETY `self.fcitx5_osk_key_helper_controller` will be initialized if not
             let fcitx5_osk_key_helper_controller =
@@ -463,6 +514,8 @@ impl Fcitx5Services {
                     key_helper_serial: None,
                     key_helper_created: false,
                     fcitx5_osk_key_helper_controller: MaybeUninit::uninit(),
+                    key_helper_pressed: vec![],
+                    key_helper_lease_handle: None,
                     modifier_workaround_keycodes,
                 },
             ));
@@ -161,9 +161,7 @@ impl Fcitx5VirtualkeyboardImPanelService {
 pub enum Fcitx5VirtualkeyboardImPanelEvent {
     ShowVirtualKeyboard,
     HideVirtualKeyboard,
-    #[allow(unused)]
     UpdatePreeditCaret(i32),
-    #[allow(unused)]
     UpdatePreeditArea(String),
     UpdateCandidateArea(Arc<CandidateAreaState>),
 }
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, path::PathBuf, rc::Rc, result::Result as StdResult};
+use std::{collections::HashMap, iter, path::PathBuf, rc::Rc, result::Result as StdResult};
 
 use getset::{CopyGetters, Getters};
 use iced::Font;
@@ -8,7 +8,7 @@ use serde::{
 };
 use xkeysym::Keysym;
 
-use crate::{font, store::IdAndConfigPath};
+use crate::{font, store::IdAndConfigPath, widget::Direction};
 
 #[derive(Deserialize)]
 struct RawKeyValue {
@@ -22,6 +22,63 @@ struct RawKeyValue {
     keycode: Option<i16>,
     #[serde(alias = "f")]
     font: Option<String>,
+    #[serde(alias = "a")]
+    action: Option<KeyAction>,
+    #[serde(default, alias = "m")]
+    modifiers: Vec<ChordModifier>,
+}
+
+/// A modifier pressed along with the key, so a shortcut can be sent by one key.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "snake_case")]
+pub enum ChordModifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+impl ChordModifier {
+    pub fn keysym(&self) -> Keysym {
+        match self {
+            ChordModifier::Shift => Keysym::Shift_L,
+            ChordModifier::Ctrl => Keysym::Control_L,
+            ChordModifier::Alt => Keysym::Alt_L,
+            ChordModifier::Super => Keysym::Super_L,
+        }
+    }
+
+    /// The keycode(x11 variant) of the left one.
+    pub fn keycode(&self) -> u32 {
+        match self {
+            ChordModifier::Shift => 50,
+            ChordModifier::Ctrl => 37,
+            ChordModifier::Alt => 64,
+            ChordModifier::Super => 133,
+        }
+    }
+}
+
+/// An action handled by the keyboard itself, no key event will be sent to fcitx5.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyAction {
+    /// Switch to a layer of the current layout, the default layer is used if the name is empty.
+    SwitchLayer(String),
+    /// Hide the keyboard, the same as the hide button in the toolbar.
+    HideKeyboard,
+    /// Select the next input method in the group.
+    NextIm,
+    /// Select the previous input method in the group.
+    PrevIm,
+    /// Show or hide the setting.
+    ToggleSetting,
+    /// Switch between dock and float.
+    TogglePlacement,
+    /// Arm a dead key, the next key is combined by the table of the same name in `dead_keys`.
+    DeadKey(String),
+    /// Start a compose sequence, the following keys are matched against `compose`.
+    Compose,
 }
 
 #[derive(CopyGetters, Getters)]
@@ -34,14 +91,22 @@ pub struct KeyValue {
     keycode: Option<i16>,
     #[getset(get_copy = "pub")]
     font: Option<Font>,
+    #[getset(get = "pub")]
+    action: Option<KeyAction>,
+    #[getset(get = "pub")]
+    modifiers: Vec<ChordModifier>,
 }
 
-#[derive(CopyGetters, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(CopyGetters, Getters, Clone, Debug, PartialEq, Eq)]
 pub struct ThinKeyValue {
     #[getset(get_copy = "pub")]
     keysym: Keysym,
     #[getset(get_copy = "pub")]
     keycode: Option<i16>,
+    #[getset(get = "pub")]
+    action: Option<KeyAction>,
+    #[getset(get = "pub")]
+    modifiers: Vec<ChordModifier>,
 }
 
 impl<'de> Deserialize<'de> for KeyValue {
@@ -54,20 +119,31 @@ impl<'de> Deserialize<'de> for KeyValue {
             Keysym::from(ks)
         } else if let Some(c) = raw.character {
             Keysym::from_char(c)
+        } else if raw.action.is_some() {
+            Keysym::NoSymbol
         } else {
             return Err(Error::missing_field("ks or c"));
         };
         let symbol = if let Some(symbol) = raw.symbol {
             symbol
+        } else if raw.action.is_some() && keysym == Keysym::NoSymbol {
+            return Err(Error::missing_field("s"));
         } else {
-            match keysym.key_char() {
+            let symbol = match keysym.key_char() {
                 Some(c) if !c.is_control() && !c.is_whitespace() => c.to_string(),
                 _ => keysym
                     .name()
                     .and_then(|n| n.splitn(2, "_").last())
                     .unwrap_or("Unknown")
                     .to_string(),
-            }
+            };
+            // e.g. Ctrl+c
+            raw.modifiers
+                .iter()
+                .map(ChordModifier::to_string)
+                .chain([symbol])
+                .collect::<Vec<_>>()
+                .join("+")
         };
         if let Some(keycode) = raw.keycode {
             // check the abs of keycode is smaller than 256.
@@ -84,6 +160,8 @@ impl<'de> Deserialize<'de> for KeyValue {
             keysym,
             keycode: raw.keycode,
             font: raw.font.as_deref().map(font::load),
+            action: raw.action,
+            modifiers: raw.modifiers,
         })
     }
 }
@@ -93,16 +171,58 @@ impl KeyValue {
         ThinKeyValue {
             keysym: self.keysym,
             keycode: self.keycode,
+            action: self.action.clone(),
+            modifiers: self.modifiers.clone(),
         }
     }
 }
 
+impl ThinKeyValue {
+    /// A value without keycode and action, it is sent by keysym only.
+    pub fn new(keysym: Keysym) -> Self {
+        Self {
+            keysym,
+            keycode: None,
+            action: None,
+            modifiers: vec![],
+        }
+    }
+
+    /// Press the modifiers along with this value.
+    pub fn with_modifiers(mut self, modifiers: Vec<ChordModifier>) -> Self {
+        self.modifiers = modifiers;
+        self
+    }
+}
+
 #[derive(Deserialize)]
 struct RawKey {
     #[serde(alias = "p")]
     primary: KeyValue,
     #[serde(default, alias = "s")]
     secondaries: Vec<KeyValue>,
+    /// Repeat the key while it is held. It is ignored if the key has secondaries.
+    #[serde(default, alias = "r")]
+    repeat: bool,
+    /// Values typed by flicking the key to a direction.
+    #[serde(default, alias = "fl")]
+    flicks: Flicks,
+    /// Dragging the key moves the text cursor if the trackpad is enabled. Default to true for the
+    /// space key.
+    #[serde(alias = "tp")]
+    trackpad: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+struct Flicks {
+    #[serde(alias = "u")]
+    up: Option<KeyValue>,
+    #[serde(alias = "d")]
+    down: Option<KeyValue>,
+    #[serde(alias = "l")]
+    left: Option<KeyValue>,
+    #[serde(alias = "r")]
+    right: Option<KeyValue>,
 }
 
 #[derive(Clone)]
@@ -116,18 +236,50 @@ impl Key {
     }
 
     pub fn key_value(&self, shift: bool, caps_lock: bool) -> ThinKeyValue {
-        let key_value = if Self::is_shifted(shift, caps_lock) {
+        self.full_key_value(shift, caps_lock).to_thin()
+    }
+
+    /// The value with its symbol and font.
+    pub fn full_key_value(&self, shift: bool, caps_lock: bool) -> &KeyValue {
+        if Self::is_shifted(shift, caps_lock) {
             self.raw.secondaries.first().unwrap_or(&self.raw.primary)
         } else {
             &self.raw.primary
-        };
-        key_value.to_thin()
+        }
     }
 
     pub fn has_secondary(&self) -> bool {
         !self.raw.secondaries.is_empty()
     }
 
+    pub fn is_repeatable(&self) -> bool {
+        self.raw.repeat && !self.has_secondary() && !self.has_flick()
+    }
+
+    pub fn has_flick(&self) -> bool {
+        let flicks = &self.raw.flicks;
+        flicks.up.is_some()
+            || flicks.down.is_some()
+            || flicks.left.is_some()
+            || flicks.right.is_some()
+    }
+
+    pub fn is_trackpad(&self) -> bool {
+        self.raw
+            .trackpad
+            .unwrap_or(self.raw.primary.keysym == Keysym::space)
+    }
+
+    pub fn flick(&self, direction: Direction) -> Option<&KeyValue> {
+        let flicks = &self.raw.flicks;
+        match direction {
+            Direction::Up => flicks.up.as_ref(),
+            Direction::Down => flicks.down.as_ref(),
+            Direction::Left => flicks.left.as_ref(),
+            Direction::Right => flicks.right.as_ref(),
+        }
+    }
+
     pub fn primary(&self) -> &KeyValue {
         &self.raw.primary
     }
@@ -154,6 +306,45 @@ pub struct KeySet {
     name: String,
     #[getset(get = "pub")]
     keys: HashMap<String, Key>,
+    /// Tables of dead keys, a char typed after the dead key is replaced by the text in its table.
+    /// The text of a space is the accent itself, it is typed before a char not in the table.
+    #[getset(get = "pub")]
+    #[serde(default)]
+    dead_keys: HashMap<String, HashMap<char, String>>,
+    /// Sequences typed after a compose key and their texts.
+    #[getset(get = "pub")]
+    #[serde(default)]
+    compose: HashMap<String, String>,
+}
+
+impl KeySet {
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = vec![];
+        let mut keys: Vec<_> = self.keys.iter().collect();
+        keys.sort_unstable_by_key(|(k, _)| *k);
+        for (name, key) in keys {
+            for key_value in iter::once(key.primary()).chain(key.secondaries()) {
+                match key_value.action() {
+                    Some(KeyAction::DeadKey(dead_key))
+                        if !self.dead_keys.contains_key(dead_key) =>
+                    {
+                        problems.push(format!(
+                            "key_set[{}] key[{name}]: dead_keys.{dead_key} is not found",
+                            self.name
+                        ));
+                    }
+                    Some(KeyAction::Compose) if self.compose.is_empty() => {
+                        problems.push(format!(
+                            "key_set[{}] key[{name}]: compose is empty",
+                            self.name
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        problems
+    }
 }
 
 impl IdAndConfigPath for KeySet {
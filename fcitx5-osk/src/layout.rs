@@ -7,13 +7,14 @@ use iced::{
     alignment::{Horizontal, Vertical},
     widget::{
         button::Style as ButtonStyle,
+        container::Style as ContainerStyle,
         scrollable::{Direction, Scrollbar},
         text::Shaping,
         text_input::TextInput,
         Button, Column, Container, PickList, Row, Scrollable, Space, Svg, Text, Toggler,
     },
     window::Id,
-    Color, Element, Font, Length,
+    Color, Element, Font, Length, Point, Rectangle, Size,
 };
 use serde::{
     de::{Error, Unexpected},
@@ -21,21 +22,19 @@ use serde::{
 };
 
 use std::{
-    collections::HashMap, path::PathBuf, result::Result as StdResult, sync::Arc, time::Duration,
+    collections::HashMap, iter, path::PathBuf, result::Result as StdResult, sync::Arc,
+    time::Duration,
 };
 
 use crate::{
     app::Message,
-    config::IndicatorDisplay,
-    dbus::server::ImPanelEvent,
     state::{
-        BoolDesc, CloseOpSource, DynamicEnumDesc, EnumDesc, Field, FieldType, ImEvent, LayoutEvent,
-        OwnedEnumDesc, StateExtractor, StepDesc, TextDesc, UpdateConfigEvent, WindowEvent,
-        WindowManagerEvent,
+        BoolDesc, ClipboardEvent, DynamicEnumDesc, EnumDesc, Field, FieldType, ImEvent,
+        LayoutEvent, OwnedEnumDesc, PredictEvent, StateExtractor, StepDesc, TextDesc,
+        UpdateConfigEvent, WindowEvent, WindowManagerEvent,
     },
-    store::IdAndConfigPath,
+    store::{IdAndConfigPath, Store},
     widget::{self, Movable, Toggle, ToggleCondition},
-    window::WindowManagerMode,
 };
 
 #[derive(Deserialize, CopyGetters, Getters)]
@@ -47,8 +46,10 @@ pub struct KeyAreaLayout {
     #[serde(alias = "spacing", default = "KeyAreaLayout::default_spacing_u")]
     spacing_u: u16,
     elements: Vec<KeyRow>,
-    #[getset(get = "pub")]
     key_mappings: HashMap<String, KeyId>,
+    /// Named layers, like symbols or numbers. The top level elements are the default layer.
+    #[serde(default)]
+    layers: HashMap<String, KeyAreaLayer>,
     #[serde(
         alias = "primary_text_size",
         default = "KeyAreaLayout::default_primary_text_size_u"
@@ -81,9 +82,32 @@ pub struct KeyAreaLayout {
     min_toolbar_height_u: u16,
     #[getset(get = "pub")]
     font: Option<String>,
+    #[serde(default)]
+    #[getset(get_copy = "pub")]
+    label_style: KeyLabelStyle,
+    /// Turn it off to never show the key preview, whatever the config is.
+    #[serde(default = "KeyAreaLayout::default_key_preview")]
+    #[getset(get_copy = "pub")]
+    key_preview: bool,
+}
+
+/// How the labels of a key with a secondary value are rendered.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyLabelStyle {
+    /// The main label is the value which will be typed, it changes with Shift and Caps Lock.
+    Live,
+    /// The primary value is always the main label, and the secondary one is always the small
+    /// label.
+    #[default]
+    Dual,
 }
 
 impl KeyAreaLayout {
+    fn default_key_preview() -> bool {
+        true
+    }
+
     fn default_spacing_u() -> u16 {
         1
     }
@@ -108,37 +132,146 @@ impl KeyAreaLayout {
         6
     }
 
-    pub fn width_u(&self) -> u16 {
-        self.elements.iter().map(KeyRow::width_u).max().unwrap_or(0)
+    pub fn has_layer(&self, layer: &str) -> bool {
+        self.layers.contains_key(layer)
     }
 
-    pub fn height_u(&self) -> u16 {
-        if self.elements.is_empty() {
+    /// Return the rows and the spacing between rows of a layer, the default layer is returned if
+    /// `layer` is None or not found.
+    fn rows(&self, layer: Option<&str>) -> (&[KeyRow], u16) {
+        match layer.map(|l| (l, self.layers.get(l))) {
+            Some((_, Some(layer))) => (&layer.elements, layer.spacing_u.unwrap_or(self.spacing_u)),
+            Some((name, None)) => {
+                tracing::warn!("layer[{name}] is not found in layout[{}]", self.name);
+                (&self.elements, self.spacing_u)
+            }
+            None => (&self.elements, self.spacing_u),
+        }
+    }
+
+    /// Key mappings of a layer, the mappings of the default layer are used if a key isn't mapped
+    /// in the layer.
+    pub fn key_mappings(&self, layer: Option<&str>) -> HashMap<&String, &KeyId> {
+        let mut key_mappings: HashMap<_, _> = self.key_mappings.iter().collect();
+        if let Some(layer) = layer.and_then(|l| self.layers.get(l)) {
+            key_mappings.extend(layer.key_mappings.iter());
+        }
+        key_mappings
+    }
+
+    pub fn width_u(&self, layer: Option<&str>) -> u16 {
+        let (rows, _) = self.rows(layer);
+        rows.iter().map(KeyRow::width_u).max().unwrap_or(0)
+    }
+
+    pub fn height_u(&self, layer: Option<&str>) -> u16 {
+        let (rows, spacing_u) = self.rows(layer);
+        if rows.is_empty() {
             return 0;
         }
-        let mut height_u = self.spacing_u * (self.elements.len() as u16 - 1);
-        height_u += self.elements.iter().map(KeyRow::height_u).sum::<u16>();
+        let mut height_u = spacing_u * (rows.len() as u16 - 1);
+        height_u += rows.iter().map(KeyRow::height_u).sum::<u16>();
         height_u
     }
 
-    pub fn size(&self, unit: u16) -> (u16, u16) {
-        (self.width_u() * unit, self.height_u() * unit)
+    pub fn size(&self, unit: u16, layer: Option<&str>) -> (u16, u16) {
+        (self.width_u(layer) * unit, self.height_u(layer) * unit)
     }
 
     pub fn to_element<'b>(
         &self,
         unit: u16,
+        layer: Option<&str>,
         state: &'b dyn StateExtractor,
     ) -> impl Into<Element<'b, Message>> {
+        let (rows, spacing_u) = self.rows(layer);
         let mut col = Column::new()
-            .spacing(self.spacing_u * unit)
+            .spacing(spacing_u * unit)
             .align_x(Horizontal::Center);
 
-        for key_row in &self.elements {
+        for key_row in rows {
             col = col.push(key_row.to_element(unit, state));
         }
 
-        col
+        state.keyboard().swipe_area(
+            col,
+            unit,
+            state.config().gesture_typing(),
+            self.key_preview && state.key_preview_enabled(),
+        )
+    }
+
+    /// Check keys without mappings, mappings which can't be resolved and rows whose width differs
+    /// from the widest one in each layer.
+    pub fn validate(&self, store: &Store) -> Vec<String> {
+        let mut problems = vec![];
+        let layers = iter::once(None).chain(self.layers.keys().map(|l| Some(l.as_str())));
+        for layer in layers {
+            let prefix = if let Some(layer) = layer {
+                format!("layout[{}] layer[{layer}]", self.name)
+            } else {
+                format!("layout[{}]", self.name)
+            };
+            let (rows, _) = self.rows(layer);
+            let key_mappings = self.key_mappings(layer);
+            let width_u = self.width_u(layer);
+            for (index, key_row) in rows.iter().enumerate() {
+                if key_row.width_u() != width_u {
+                    problems.push(format!(
+                        "{prefix}: the width of row[{index}] is {}, but the widest one is {width_u}",
+                        key_row.width_u()
+                    ));
+                }
+                for element in &key_row.elements {
+                    if let KeyRowElement::Key { name, .. } = element {
+                        if !key_mappings.contains_key(&name.to_string()) {
+                            problems.push(format!("{prefix}: key[{name}] has no mapping"));
+                        }
+                    }
+                }
+            }
+            let mut key_mappings: Vec<_> = key_mappings.into_iter().collect();
+            key_mappings.sort_unstable_by_key(|(k, _)| *k);
+            for (name, key_id) in key_mappings {
+                if let Some(problem) = store.check_key_id(key_id) {
+                    problems.push(format!("{prefix}: mapping of {name}, {problem}"));
+                }
+            }
+        }
+        problems
+    }
+
+    /// The bounds of each key, they are relative to the top left corner of the key area and in
+    /// unit.
+    pub fn key_bounds_u(&self, layer: Option<&str>) -> Vec<(Arc<str>, Rectangle)> {
+        let width_u = self.width_u(layer) as f32;
+        let (rows, spacing_u) = self.rows(layer);
+        let mut bounds = vec![];
+        let mut y = 0.;
+        for key_row in rows {
+            // rows are aligned to the center.
+            let mut x = (width_u - key_row.width_u() as f32) / 2.;
+            for element in &key_row.elements {
+                if let KeyRowElement::Key {
+                    width_u,
+                    height_u,
+                    name,
+                } = element
+                {
+                    let height_u =
+                        height_u.unwrap_or(key_row.height_u).min(key_row.height_u) as f32;
+                    // keys are aligned to the center of a row.
+                    let top = y + (key_row.height_u as f32 - height_u) / 2.;
+                    bounds.push((
+                        name.clone(),
+                        Rectangle::new(Point::new(x, top), Size::new(*width_u as f32, height_u)),
+                    ));
+                }
+                x += (element.width_u() + key_row.spacing_u) as f32;
+            }
+            y += (key_row.height_u + spacing_u) as f32;
+        }
+        bounds
     }
 }
 
@@ -158,6 +291,16 @@ impl IdAndConfigPath for KeyAreaLayout {
     }
 }
 
+#[derive(Deserialize)]
+pub struct KeyAreaLayer {
+    /// vertical space between rows, the one of the layout is used if it isn't set.
+    #[serde(alias = "spacing")]
+    spacing_u: Option<u16>,
+    elements: Vec<KeyRow>,
+    #[serde(default)]
+    key_mappings: HashMap<String, KeyId>,
+}
+
 #[derive(Getters)]
 pub struct KeyId {
     #[getset(get = "pub")]
@@ -370,13 +513,123 @@ impl ToolbarLayout {
         candidate_font: Font,
         font_size_u: u16,
     ) -> Element<'b, Message> {
-        if params.state.im().candidate_area_state().has_candidate() {
-            self.to_candidate_element(params, unit, candidate_font, font_size_u)
+        let im = params.state.im();
+        let has_candidate = im.candidate_area_state().has_candidate();
+        let has_preedit = !im.preedit_state().is_empty();
+        let has_prediction = !has_candidate
+            && params.state.config().word_prediction()
+            && !params.state.predict().candidates().is_empty();
+        if has_prediction {
+            self.to_prediction_element(params, unit, candidate_font, font_size_u)
+        } else if has_candidate || has_preedit {
+            let mut column = Column::new().width(Length::Fill).height(Length::Fill);
+            if has_preedit {
+                column =
+                    column.push(self.to_preedit_element(params, unit, candidate_font, font_size_u));
+            }
+            if has_candidate {
+                column = column.push(self.to_candidate_element(
+                    params,
+                    unit,
+                    candidate_font,
+                    font_size_u,
+                ));
+            }
+            column.into()
         } else {
             self.to_toolbar_element(params, unit, font_size_u)
         }
     }
 
+    /// Show the preedit with its caret, tap a char to move the caret before it.
+    fn to_preedit_element<'a, 'b>(
+        &'a self,
+        params: &'a ToElementCommonParams<'b>,
+        unit: u16,
+        font: Font,
+        font_size_u: u16,
+    ) -> Element<'b, Message> {
+        let theme = params.state.theme();
+        let state = params.state.im().preedit_state();
+        // preedit is smaller than candidates
+        let font_size = (font_size_u * unit * 2 / 3).max(1);
+        let color = theme.extended_palette().background.weak.text;
+        let caret_color = theme.extended_palette().primary.base.color;
+        let caret = state.caret_char_index();
+
+        let caret_element = || {
+            Container::new(Space::new(Length::Shrink, Length::Shrink))
+                .width(2)
+                .height(font_size)
+                .style(move |_| ContainerStyle::default().background(caret_color))
+        };
+        let mut row = Row::new().align_y(Vertical::Center);
+        let mut len = 0;
+        for (index, c) in state.text().chars().enumerate() {
+            if caret == Some(index) {
+                row = row.push(caret_element());
+            }
+            let text = Text::new(c.to_string())
+                .font(font)
+                .shaping(Shaping::Advanced)
+                .size(font_size)
+                .color(color);
+            row = row.push(
+                Button::new(text)
+                    .style(|_, _| ButtonStyle::default().with_background(Color::TRANSPARENT))
+                    .padding(0)
+                    .on_press(ImEvent::MovePreeditCaret(index).into()),
+            );
+            len = index + 1;
+        }
+        if caret == Some(len) {
+            row = row.push(caret_element());
+        }
+        // tap the blank area to move the caret to the end.
+        row = row.push(
+            Button::new(Space::new(Length::Fill, font_size))
+                .width(Length::Fill)
+                .style(|_, _| ButtonStyle::default().with_background(Color::TRANSPARENT))
+                .padding(0)
+                .on_press(ImEvent::MovePreeditCaret(len).into()),
+        );
+
+        Container::new(row)
+            .width(Length::Fill)
+            .padding([0, 2 * unit])
+            .into()
+    }
+
+    /// Show the words predicted by the keyboard itself, as many as the width allows.
+    fn to_prediction_element<'a, 'b>(
+        &'a self,
+        params: &'a ToElementCommonParams<'b>,
+        unit: u16,
+        font: Font,
+        font_size_u: u16,
+    ) -> Element<'b, Message> {
+        let candidates = params.state.predict().candidates();
+        let spacing = 2 * unit;
+        let font_size = font_size_u * unit;
+        let available_width = params.state.available_candidate_width();
+
+        let mut row = Row::new().spacing(spacing).align_y(Vertical::Center);
+        let mut consumed_width = 0;
+        for (index, candidate) in candidates.iter().enumerate() {
+            // TODO Simply assume one char consumes 1 * font_size, the same as candidates of fcitx5.
+            let width = candidate.chars().count() as u16 * font_size;
+            consumed_width += width + spacing;
+            if index > 0 && consumed_width > available_width {
+                break;
+            }
+            row = row.push(
+                candidate_btn(candidate, font, font_size, width)
+                    .on_press(PredictEvent::Select(index).into()),
+            );
+        }
+        Container::new(row).center(Length::Fill).into()
+    }
+
     fn to_candidate_element<'a, 'b>(
         &'a self,
         params: &'a ToElementCommonParams<'b>,
@@ -510,22 +763,9 @@ impl ToolbarLayout {
             .align_y(Vertical::Center)
             .spacing(unit * 2);
 
-        let indicator_message = match state.indicator_display() {
-            IndicatorDisplay::Auto => Some(WindowManagerEvent::OpenIndicator.into()),
-            IndicatorDisplay::AlwaysOn => {
-                Some(WindowManagerEvent::CloseKeyboard(CloseOpSource::UserAction).into())
-            }
-            IndicatorDisplay::AlwaysOff => {
-                if state.window_manager_mode() == WindowManagerMode::KwinLockScreen {
-                    Some(ImPanelEvent::NewVisibleRequest(false).into())
-                } else {
-                    Some(WindowManagerEvent::CloseKeyboard(CloseOpSource::UserAction).into())
-                }
-            }
-        };
-        if let Some(message) = indicator_message {
-            row = row.push(nerd_btn('󰁄', font_size, color, unit).on_press(message));
-        }
+        row = row.push(
+            nerd_btn('󰁄', font_size, color, unit).on_press(WindowManagerEvent::HideKeyboard.into()),
+        );
 
         // padding
         let window_id = params.window_id;
@@ -576,6 +816,11 @@ impl ToolbarLayout {
                     .text_size(font_size),
                 ),
         );
+        if state.clipboard_history_enabled() {
+            row = row.push(
+                nerd_btn('󰅇', font_size, color, unit).on_press(LayoutEvent::ToggleClipboard.into()),
+            );
+        }
         row = row.push(
             nerd_btn('󰘮', font_size, color, unit).on_press(LayoutEvent::ToggleSetting.into()),
         );
@@ -632,6 +877,97 @@ impl SettingLayout {
     }
 }
 
+pub struct ClipboardLayout;
+
+impl ClipboardLayout {
+    /// The max number of chars shown for an entry.
+    const PREVIEW_LEN: usize = 40;
+
+    pub fn to_element<'a, 'b>(
+        &'a self,
+        params: &'a ToElementCommonParams<'b>,
+        unit: u16,
+        font_size_u: u16,
+    ) -> Element<'b, Message> {
+        let state = params.state;
+        let theme = state.theme();
+        let color = theme.extended_palette().background.weak.text;
+        let pinned_color = theme.extended_palette().primary.base.color;
+        let text_size = font_size_u * unit;
+        let height = text_size + 4 * unit;
+        let entries = state.clipboard().entries();
+        if entries.is_empty() {
+            return Container::new(
+                Text::new("Copied texts will be shown here")
+                    .size(text_size)
+                    .color(color),
+            )
+            .center(Length::Fill)
+            .into();
+        }
+
+        let mut column = Column::new().width(Length::Fill);
+        for (index, entry) in entries.iter().enumerate() {
+            // show an entry in one line
+            let mut preview = entry
+                .text()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if let Some((pos, _)) = preview.char_indices().nth(Self::PREVIEW_LEN) {
+                preview.truncate(pos);
+                preview.push('…');
+            }
+            let (pin_icon, pin_color) = if entry.pinned() {
+                ('󰐃', pinned_color)
+            } else {
+                ('󰐄', color)
+            };
+            column = column.push(
+                Row::new()
+                    .height(height)
+                    .align_y(Vertical::Center)
+                    .push(
+                        Button::new(
+                            Container::new(
+                                Text::new(preview)
+                                    .size(text_size)
+                                    .shaping(Shaping::Advanced)
+                                    .color(color),
+                            )
+                            .center_y(Length::Fill),
+                        )
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .style(|_, _| ButtonStyle::default().with_background(Color::TRANSPARENT))
+                        .padding(0)
+                        .on_press(ClipboardEvent::Paste(index).into()),
+                    )
+                    .push(
+                        nerd_btn(pin_icon, text_size, pin_color, unit)
+                            .on_press(ClipboardEvent::TogglePin(index).into()),
+                    )
+                    .push(
+                        nerd_btn('󰆴', text_size, color, unit)
+                            .on_press(ClipboardEvent::Remove(index).into()),
+                    ),
+            );
+        }
+        Container::new(
+            Scrollable::with_direction(
+                Row::new()
+                    .push(column)
+                    // don't overlap with the scrollbar
+                    .push(Column::new().width(2 * unit)),
+                Direction::Vertical(Scrollbar::new().width(unit).scroller_width(unit)),
+            )
+            .style(widget::scrollable_style),
+        )
+        .height(Length::Fill)
+        .into()
+    }
+}
+
 pub struct ToElementCommonParams<'a> {
     pub state: &'a dyn StateExtractor,
     pub window_id: Id,
@@ -11,6 +11,7 @@ use clap::{Parser, Subcommand};
 use config::{Config, ConfigManager};
 use fcitx5_osk_common::dbus::client::Fcitx5OskControllerServiceProxy;
 use iced::Task;
+use store::Store;
 use window::{wayland, x11};
 use zbus::Connection;
 
@@ -20,6 +21,7 @@ mod dbus;
 mod font;
 mod key_set;
 mod layout;
+mod predict;
 mod state;
 mod store;
 mod theme;
@@ -43,6 +45,17 @@ enum Command {
     GetProperty { name: String },
     /// Set the value of a property.
     SetProperty { name: String, value: String },
+    /// Check layouts, key sets and themes in the configured folders, exit with non-zero if there
+    /// is any problem.
+    Validate(ValidateArgs),
+}
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct ValidateArgs {
+    /// The path of config file.
+    #[arg(short, long, value_name = "PATH")]
+    config: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -94,8 +107,10 @@ fn load_external_fonts(config: &Config) -> Result<()> {
 fn run(args: Args) -> Result<()> {
     let config_path = match &args.command {
         Command::Keyboard(keyboard_args) => keyboard_args.config.as_ref(),
+        Command::Validate(validate_args) => validate_args.config.as_ref(),
         _ => None,
     };
+    let is_validate = matches!(args.command, Command::Validate(_));
 
     let config_path = if let Ok(path) = env::var("FCITX5_OSK_CONFIG") {
         Some(Path::new(&path).to_path_buf())
@@ -120,8 +135,9 @@ fn run(args: Args) -> Result<()> {
     let (config_manager, config_write_bg) = match ConfigManager::new(config_path.as_ref()) {
         Ok(r) => r,
         Err(e) => {
-            if config_path.is_none() {
-                // It shouldn't happen, default config should be able to built.
+            if config_path.is_none() || is_validate {
+                // It shouldn't happen, default config should be able to built. And a broken
+                // config is a problem to be reported when validating.
                 return Err(e);
             } else {
                 eprintln!(
@@ -149,6 +165,9 @@ fn run(args: Args) -> Result<()> {
         Command::SetProperty { name, value } => {
             return async_run(async move || set_property(name, value).await);
         }
+        Command::Validate(_) => {
+            return validate(config_manager.as_ref());
+        }
     };
 
     load_external_fonts(config_manager.as_ref())?;
@@ -217,6 +236,18 @@ fn run(args: Args) -> Result<()> {
     }
 }
 
+fn validate(config: &Config) -> Result<()> {
+    let problems = Store::validate(config)?;
+    if problems.is_empty() {
+        println!("No problem found");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    anyhow::bail!("{} problem(s) found", problems.len());
+}
+
 async fn force_show_keyboard() -> Result<()> {
     let connection = Connection::session().await?;
     let controller = Fcitx5OskControllerServiceProxy::new(&connection).await?;
@@ -0,0 +1,186 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    future::Future,
+    io,
+    ops::Bound,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context as _, Result};
+use iced::futures::lock::Mutex as IcedFuturesMutex;
+
+use crate::config::Config;
+
+const WORD_LIST_FILE: &str = "words.txt";
+
+const USER_DICTIONARY_FILE: &str = "user_words.txt";
+
+/// The weight of a learned word, so that words used by the user are ranked before others.
+const USER_WORD_WEIGHT: u64 = 1000;
+
+/// Words for prediction. Words are stored in lower case, the frequency of a word is used to
+/// rank the completions.
+#[derive(Default)]
+pub struct Dictionary {
+    words: BTreeMap<String, u64>,
+    user_words: BTreeMap<String, u64>,
+    user_dictionary_path: Option<PathBuf>,
+}
+
+impl Dictionary {
+    /// Load the word list and the user-learned dictionary, a missing file is treated as empty.
+    ///
+    /// Each line of the files is a word and an optional frequency separated by whitespace, lines
+    /// starting with '#' are ignored.
+    pub fn load(config: &Config) -> Result<Self> {
+        let word_list_path = config
+            .word_list_path()
+            .cloned()
+            .or_else(|| default_path(WORD_LIST_FILE));
+        let user_dictionary_path = config
+            .user_dictionary_path()
+            .cloned()
+            .or_else(|| default_path(USER_DICTIONARY_FILE));
+        Ok(Self {
+            words: read_words(word_list_path.as_deref())?,
+            user_words: read_words(user_dictionary_path.as_deref())?,
+            user_dictionary_path,
+        })
+    }
+
+    /// Return at most `limit` words starting with `prefix`, the prefix itself is excluded.
+    pub fn predict(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let prefix = prefix.to_lowercase();
+        if prefix.is_empty() {
+            return vec![];
+        }
+        let mut words: BTreeMap<&str, u64> = BTreeMap::new();
+        for (word, frequency) in completions(&self.words, &prefix) {
+            *words.entry(word).or_default() += frequency;
+        }
+        for (word, count) in completions(&self.user_words, &prefix) {
+            *words.entry(word).or_default() += count * USER_WORD_WEIGHT;
+        }
+        let mut words: Vec<_> = words.into_iter().filter(|(w, _)| *w != prefix).collect();
+        // sort by frequency, then by word
+        words.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        words
+            .into_iter()
+            .take(limit)
+            .map(|(w, _)| w.to_string())
+            .collect()
+    }
+
+    /// Learn a word typed by the user. Return the path and the content of the user dictionary to
+    /// be written.
+    pub fn learn(&mut self, word: &str) -> Option<(PathBuf, String)> {
+        let word = word.to_lowercase();
+        // a single letter is not a word to be completed.
+        if word.chars().count() < 2 {
+            return None;
+        }
+        *self.user_words.entry(word).or_default() += 1;
+        let path = self.user_dictionary_path.clone()?;
+        let content = self
+            .user_words
+            .iter()
+            .map(|(w, c)| format!("{w} {c}\n"))
+            .collect();
+        Some((path, content))
+    }
+}
+
+/// Writes the user dictionary one by one. Each write has the whole dictionary, so a write older
+/// than the written one is skipped.
+#[derive(Default)]
+pub struct DictionaryWriter {
+    version: u64,
+    written_version: Arc<IcedFuturesMutex<u64>>,
+}
+
+impl DictionaryWriter {
+    pub fn write(
+        &mut self,
+        path: PathBuf,
+        content: String,
+    ) -> impl Future<Output = io::Result<()>> + 'static {
+        self.version += 1;
+        let version = self.version;
+        let written_version = self.written_version.clone();
+        async move {
+            let mut written_version = written_version.lock().await;
+            if *written_version > version {
+                return Ok(());
+            }
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            // write to a temporary file first, so the dictionary is never partially written.
+            let mut tmp_path = path.clone().into_os_string();
+            tmp_path.push(".tmp");
+            tokio::fs::write(&tmp_path, content).await?;
+            tokio::fs::rename(&tmp_path, &path).await?;
+            *written_version = version;
+            Ok(())
+        }
+    }
+}
+
+fn completions<'a>(
+    words: &'a BTreeMap<String, u64>,
+    prefix: &'a str,
+) -> impl Iterator<Item = (&'a str, u64)> {
+    words
+        .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+        .take_while(move |(w, _)| w.starts_with(prefix))
+        .map(|(w, f)| (w.as_str(), *f))
+}
+
+fn read_words(path: Option<&Path>) -> Result<BTreeMap<String, u64>> {
+    let Some(path) = path else {
+        return Ok(Default::default());
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Default::default()),
+        Err(e) => return Err(e).with_context(|| format!("Unable to read {:?}", path)),
+    };
+    let mut words = BTreeMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut items = line.split_whitespace();
+        let Some(word) = items.next() else {
+            continue;
+        };
+        let frequency = match items.next() {
+            Some(f) => f
+                .parse()
+                .with_context(|| format!("Invalid frequency of word[{word}] in {:?}", path))?,
+            None => 1,
+        };
+        *words.entry(word.to_lowercase()).or_default() += frequency;
+    }
+    tracing::debug!("Load {} words from {:?}", words.len(), path);
+    Ok(words)
+}
+
+/// The path of a file under `$XDG_CONFIG_HOME/fcitx5-osk`.
+fn default_path(file_name: &str) -> Option<PathBuf> {
+    let mut buf = if let Ok(config_home_path) = env::var("XDG_CONFIG_HOME") {
+        PathBuf::from(config_home_path)
+    } else if let Ok(home_path) = env::var("HOME") {
+        let mut buf = PathBuf::from(home_path);
+        buf.push(".config");
+        buf
+    } else {
+        return None;
+    };
+    buf.push("fcitx5-osk");
+    buf.push(file_name);
+    Some(buf)
+}
@@ -1,8 +1,9 @@
 use std::{
     future::Future,
+    path::PathBuf,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc,
+        Arc, Mutex,
     },
 };
 
@@ -12,19 +13,22 @@ use iced::{window::Id, Element, Task, Theme, Vector};
 
 use crate::{
     app::{self, error_with_context, MapTask, Message},
-    config::{Config, ConfigManager, IndicatorDisplay, Placement},
+    config::{Config, ConfigManager, ContentPurpose, IndicatorDisplay, Placement},
     dbus::client::Fcitx5Services,
     layout::ToElementCommonParams,
     store::Store,
     window::{WindowManager, WindowManagerMode},
 };
 
+mod clipboard;
 mod config;
 mod im;
 mod keyboard;
 mod layout;
+mod predict;
 mod window;
 
+pub use clipboard::{ClipboardEvent, ClipboardState};
 pub use config::{
     BoolDesc, ConfigState, DynamicEnumDesc, EnumDesc, Field, FieldType, OwnedEnumDesc, StepDesc,
     TextDesc, UpdateConfigEvent,
@@ -32,6 +36,7 @@ pub use config::{
 pub use im::{ImEvent, ImState};
 pub use keyboard::{KeyEvent, KeyboardEvent, KeyboardState};
 pub use layout::{LayoutEvent, LayoutState};
+pub use predict::{PredictEvent, PredictState};
 pub use window::{CloseOpSource, WindowEvent, WindowManagerEvent, WindowManagerState};
 
 #[derive(Getters, MutGetters)]
@@ -43,11 +48,18 @@ pub struct State<WM> {
     keyboard: KeyboardState,
     #[getset(get_mut = "pub")]
     im: ImState,
+    predict: PredictState,
+    clipboard: ClipboardState,
     #[getset(get = "pub", get_mut = "pub")]
     window_manager: WindowManagerState<WM>,
     detect_theme_enabled: Arc<AtomicBool>,
+    /// The folders of the store, they are watched for reloading.
+    watched_folders: Arc<Mutex<Vec<PathBuf>>>,
     theme: Theme,
     color_theme: u32,
+    /// The purpose of the focused text field, its layout takes precedence over the one of the
+    /// input method.
+    content_purpose: ContentPurpose,
 }
 
 impl<WM> State<WM> {
@@ -56,30 +68,37 @@ impl<WM> State<WM> {
         wm: WM,
         fcitx5_services: Fcitx5Services,
         detect_theme_enabled: Arc<AtomicBool>,
+        watched_folders: Arc<Mutex<Vec<PathBuf>>>,
     ) -> Self {
         let config = config_manager.as_ref();
         let store = Store::new();
         let portrait = false;
         // key_area_layout will be updated when cur_im is updated.
-        let key_area_layout = store.key_area_layout_by_im("", portrait);
+        let (key_area_layout, layer) = store.key_area_layout_by_im("", portrait);
         let mut state = Self {
             keyboard: KeyboardState::new(
-                config.holding_timeout(),
+                config,
                 &key_area_layout,
+                layer.as_deref(),
                 &store,
                 fcitx5_services.clone(),
             ),
             im: ImState::new(fcitx5_services.clone()),
+            predict: Default::default(),
+            clipboard: Default::default(),
             window_manager: WindowManagerState::new(
                 config,
                 wm,
                 portrait,
                 key_area_layout,
+                layer,
                 fcitx5_services,
             ),
             detect_theme_enabled,
+            watched_folders,
             theme: Default::default(),
             color_theme: 0,
+            content_purpose: ContentPurpose::Normal,
             config: ConfigState::new(config_manager),
             store,
         };
@@ -169,26 +188,47 @@ where
             .or_else(|| self.im.im_name().map(String::as_str))
             .unwrap_or_default();
         let portrait = self.window_manager.is_portrait();
-        let key_area_layout = self
+        let (key_area_layout, layer) = self
             .store
-            .key_area_layout_by_im(im_name, self.window_manager.is_portrait());
+            .key_area_layout_by_content_purpose(self.content_purpose, portrait)
+            .unwrap_or_else(|| self.store.key_area_layout_by_im(im_name, portrait));
         let max_width = if portrait {
             self.config().portrait_width()
         } else {
             self.config().landscape_width()
         };
-        let task = self
-            .window_manager
-            .update_key_area_layout(max_width, key_area_layout.clone());
+        let task = self.window_manager.update_key_area_layout(
+            max_width,
+            key_area_layout.clone(),
+            layer.clone(),
+        );
         if task.is_some() {
             self.keyboard
-                .update_key_area_layout(&key_area_layout, &self.store);
+                .update_key_area_layout(&key_area_layout, layer.as_deref(), &self.store);
             self.window_manager
                 .update_candidate_font(self.store.font_by_im(im_name));
         }
         task
     }
 
+    fn switch_layer(&mut self, layer: String) -> Task<WM::Message> {
+        let key_area_layout = self.window_manager.key_area_layout().clone();
+        let layer = Some(layer).filter(|l| !l.is_empty());
+        if let Some(name) = layer.as_ref().filter(|l| !key_area_layout.has_layer(l)) {
+            tracing::warn!(
+                "layer[{name}] is not found in layout[{}]",
+                key_area_layout.name()
+            );
+            return Message::from_nothing();
+        }
+        if self.keyboard.layer() == layer.as_deref() {
+            return Message::from_nothing();
+        }
+        self.keyboard
+            .update_key_area_layer(&key_area_layout, layer.as_deref(), &self.store);
+        self.window_manager.update_key_area_layer(layer)
+    }
+
     fn update_cur_im(&mut self, im_name: &str) -> Task<WM::Message> {
         if self.im.im_name().filter(|n| *n == im_name).is_some() {
             // Don't update
@@ -196,6 +236,7 @@ where
         }
         if let Some(task) = self.update_layout_by_im(Some(im_name)) {
             self.im.update_cur_im(im_name);
+            self.predict.reset();
             task
         } else {
             Message::from_nothing()
@@ -205,6 +246,13 @@ where
     pub fn on_im_event(&mut self, event: ImEvent) -> Task<WM::Message> {
         match event {
             ImEvent::UpdateCurrentIm(im) => self.update_cur_im(&im),
+            ImEvent::SelectNextIm | ImEvent::SelectPrevIm => {
+                let next = matches!(event, ImEvent::SelectNextIm);
+                match self.im.neighbour_im(next).cloned() {
+                    Some(im) => self.on_im_event(ImEvent::SelectIm(im)),
+                    None => Message::from_nothing(),
+                }
+            }
             // make sure virtual keyboard mode of fcitx5 is activated
             ImEvent::SelectIm(_) => self
                 .keyboard_mut()
@@ -215,15 +263,50 @@ where
         }
     }
 
+    pub fn on_predict_event(&mut self, event: PredictEvent) -> Task<WM::Message> {
+        // only keyboard layouts of fcitx5 are predicted, other input methods have their own
+        // candidates.
+        let enabled = self.config.config().word_prediction()
+            && !self.im.candidate_area_state().has_candidate()
+            && self
+                .im
+                .im_name()
+                .filter(|n| n.starts_with("keyboard-"))
+                .is_some();
+        // words typed in a password field or on the lock screen are secrets.
+        let learn = self.config.config().learn_words()
+            && !matches!(
+                self.content_purpose,
+                ContentPurpose::Password | ContentPurpose::Digits
+            )
+            && self.window_manager_mode() != WindowManagerMode::KwinLockScreen;
+        self.predict.on_event(event, enabled, learn).map_task()
+    }
+
+    pub fn on_clipboard_event(&mut self, event: ClipboardEvent) -> Task<WM::Message> {
+        if matches!(event, ClipboardEvent::Copied(_)) && !self.clipboard_history_enabled() {
+            return Message::from_nothing();
+        }
+        let history_size = self.config.config().clipboard_history_size();
+        self.clipboard.on_event(event, history_size).map_task()
+    }
+
     pub fn on_layout_event(&mut self, event: LayoutEvent) -> Task<WM::Message> {
-        let task = if let LayoutEvent::SyncLayout = event {
-            self.update_layout_by_im(None)
-        } else {
-            self.window_manager.on_layout_event(event);
-            if self.window_manager.is_setting_shown() {
-                self.config.refresh();
+        let task = match event {
+            LayoutEvent::SyncLayout => self.update_layout_by_im(None),
+            LayoutEvent::SwitchLayer(layer) => Some(self.switch_layer(layer)),
+            LayoutEvent::UpdateContentPurpose(purpose) if purpose != self.content_purpose => {
+                self.content_purpose = purpose;
+                self.update_layout_by_im(None)
+            }
+            LayoutEvent::UpdateContentPurpose(_) => None,
+            event => {
+                self.window_manager.on_layout_event(event);
+                if self.window_manager.is_setting_shown() {
+                    self.config.refresh();
+                }
+                None
             }
-            None
         };
         if let Some(task) = task {
             task
@@ -237,6 +320,10 @@ where
             StoreEvent::Load => match Store::load(self.config.config()) {
                 Ok(s) => {
                     self.store = s;
+                    self.update_watched_folders();
+                    if let Err(e) = self.predict.load(self.config.config()) {
+                        tracing::warn!("Unable to load dictionary for prediction: {e:?}");
+                    }
                     // Update theme after store is changed
                     self.sync_theme(None);
                     // Update layout by cur im after store is changed
@@ -247,6 +334,30 @@ where
                     Task::done(error_with_context(e, "Unable to load `Store` from config").into())
                 }
             },
+            StoreEvent::LoadConfig => match self.config.reload() {
+                Ok(Some(messages)) => {
+                    self.detect_theme_enabled
+                        .store(self.is_auto_theme(), Ordering::SeqCst);
+                    self.keyboard.update_config(self.config.config());
+                    // Store is loaded after others are updated, so the layout is updated with the
+                    // new widths.
+                    messages
+                        .into_iter()
+                        .chain([StoreEvent::Load.into()])
+                        .map(Task::done)
+                        .fold(Message::nothing(), Task::chain)
+                        .map_task()
+                }
+                Ok(None) => Message::from_nothing(),
+                Err(e) => Task::done(error_with_context(e, "Unable to reload config").into()),
+            },
+        }
+    }
+
+    fn update_watched_folders(&self) {
+        match self.watched_folders.lock() {
+            Ok(mut folders) => *folders = Store::folders(self.config.config()),
+            Err(_) => tracing::error!("watched folders are poisoned"),
         }
     }
 }
@@ -259,6 +370,16 @@ pub trait StateExtractor {
 
     fn im(&self) -> &ImState;
 
+    fn predict(&self) -> &PredictState;
+
+    fn clipboard(&self) -> &ClipboardState;
+
+    /// Whether the clipboard history is recorded and shown.
+    fn clipboard_history_enabled(&self) -> bool;
+
+    /// Whether the key preview is shown, the layout can still turn it off.
+    fn key_preview_enabled(&self) -> bool;
+
     fn theme(&self) -> &Theme;
 
     fn theme_names(&self) -> &[String];
@@ -305,6 +426,28 @@ where
         &self.im
     }
 
+    fn predict(&self) -> &PredictState {
+        &self.predict
+    }
+
+    fn clipboard(&self) -> &ClipboardState {
+        &self.clipboard
+    }
+
+    fn clipboard_history_enabled(&self) -> bool {
+        let config = self.config.config();
+        config.clipboard_history()
+            && (self.window_manager_mode() != WindowManagerMode::KwinLockScreen
+                || config.clipboard_history_on_lock_screen())
+    }
+
+    fn key_preview_enabled(&self) -> bool {
+        let config = self.config.config();
+        config.key_preview()
+            && (self.window_manager_mode() != WindowManagerMode::KwinLockScreen
+                || config.key_preview_on_lock_screen())
+    }
+
     fn theme(&self) -> &Theme {
         &self.theme
     }
@@ -380,6 +523,8 @@ impl From<ThemeEvent> for Message {
 #[derive(Clone, Debug)]
 pub enum StoreEvent {
     Load,
+    /// Reload the config file, the store is loaded after it.
+    LoadConfig,
 }
 
 impl From<StoreEvent> for Message {
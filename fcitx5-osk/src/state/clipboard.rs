@@ -0,0 +1,98 @@
+use getset::{CopyGetters, Getters};
+use iced::Task;
+
+use crate::{app::Message, state::KeyboardEvent};
+
+#[derive(Clone, Debug, CopyGetters, Getters)]
+pub struct ClipboardEntry {
+    #[getset(get = "pub")]
+    text: String,
+    #[getset(get_copy = "pub")]
+    pinned: bool,
+}
+
+/// Recent texts of the clipboard, the latest one is the first.
+#[derive(Default)]
+pub struct ClipboardState {
+    entries: Vec<ClipboardEntry>,
+}
+
+impl ClipboardState {
+    pub fn entries(&self) -> &[ClipboardEntry] {
+        &self.entries
+    }
+
+    pub fn clear(&mut self) {
+        // pinned entries are kept until they are removed by the user.
+        self.entries.retain(|e| e.pinned);
+    }
+
+    pub fn on_event(&mut self, event: ClipboardEvent, history_size: usize) -> Task<Message> {
+        match event {
+            ClipboardEvent::Copied(text) => {
+                if text.trim().is_empty() {
+                    return Message::nothing();
+                }
+                // move it to the front if it is recorded.
+                let pinned = match self.entries.iter().position(|e| e.text == text) {
+                    Some(index) => self.entries.remove(index).pinned,
+                    None => false,
+                };
+                self.entries.insert(0, ClipboardEntry { text, pinned });
+                self.truncate(history_size);
+                Message::nothing()
+            }
+            ClipboardEvent::Paste(index) => match self.entries.get(index) {
+                Some(entry) => Task::done(KeyboardEvent::TypeText(entry.text.clone()).into()),
+                None => Message::nothing(),
+            },
+            ClipboardEvent::TogglePin(index) => {
+                if let Some(entry) = self.entries.get_mut(index) {
+                    entry.pinned = !entry.pinned;
+                }
+                self.truncate(history_size);
+                Message::nothing()
+            }
+            ClipboardEvent::Remove(index) => {
+                if index < self.entries.len() {
+                    self.entries.remove(index);
+                }
+                Message::nothing()
+            }
+            ClipboardEvent::Clear => {
+                self.clear();
+                Message::nothing()
+            }
+        }
+    }
+
+    /// Drop the oldest unpinned entries if there are more than `history_size` of them.
+    fn truncate(&mut self, history_size: usize) {
+        let mut unpinned = 0;
+        self.entries.retain(|e| {
+            if e.pinned {
+                return true;
+            }
+            unpinned += 1;
+            unpinned <= history_size
+        });
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum ClipboardEvent {
+    /// A text is copied to the clipboard by any application.
+    Copied(String),
+    /// Type the text of an entry.
+    Paste(usize),
+    TogglePin(usize),
+    Remove(usize),
+    /// Remove all unpinned entries.
+    Clear,
+}
+
+impl From<ClipboardEvent> for Message {
+    fn from(value: ClipboardEvent) -> Self {
+        Self::ClipboardEvent(value)
+    }
+}
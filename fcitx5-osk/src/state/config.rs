@@ -3,6 +3,7 @@ use std::{
     fmt::{Display, Formatter, Result as FmtResult},
 };
 
+use anyhow::Result;
 use iced::Task;
 use strum::IntoEnumIterator;
 
@@ -10,7 +11,10 @@ use crate::{
     app::Message,
     config::{Config, ConfigManager, IndicatorDisplay, Placement},
     dbus::server::ImPanelEvent,
-    state::{ImEvent, StateExtractor, ThemeEvent, WindowManagerEvent},
+    state::{
+        ClipboardEvent, ImEvent, KeyboardEvent, PredictEvent, StateExtractor, ThemeEvent,
+        WindowManagerEvent,
+    },
     window::WindowManagerMode,
 };
 
@@ -433,6 +437,66 @@ impl ConfigState {
                     }
                     .into(),
                 },
+                Field {
+                    name: "Gesture Typing",
+                    id: "gesture_typing",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().gesture_typing(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::GestureTyping(v)),
+                    }
+                    .into(),
+                },
+                Field {
+                    name: "Sticky Modifiers",
+                    id: "sticky_modifiers",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().sticky_modifiers(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::StickyModifiers(v)),
+                    }
+                    .into(),
+                },
+                Field {
+                    name: "Word Prediction",
+                    id: "word_prediction",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().word_prediction(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::WordPrediction(v)),
+                    }
+                    .into(),
+                },
+                Field {
+                    name: "Clipboard History",
+                    id: "clipboard_history",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().clipboard_history(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::ClipboardHistory(v)),
+                    }
+                    .into(),
+                },
+                Field {
+                    name: "Key Preview",
+                    id: "key_preview",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().key_preview(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::KeyPreview(v)),
+                    }
+                    .into(),
+                },
+                Field {
+                    name: "Cursor Trackpad",
+                    id: "cursor_trackpad",
+                    typ: BoolDesc {
+                        cur_value: |state| state.config().cursor_trackpad(),
+                        is_enabled: |_state| true,
+                        on_changed: |_, v| Message::from(UpdateConfigEvent::CursorTrackpad(v)),
+                    }
+                    .into(),
+                },
                 Field {
                     name: "Dark Theme",
                     id: "dark_theme",
@@ -507,6 +571,36 @@ impl ConfigState {
         &self.updatable_fields
     }
 
+    /// Read the config file again. Return the messages to apply the changed values, or None if
+    /// nothing is changed.
+    pub fn reload(&mut self) -> Result<Option<Vec<Message>>> {
+        let old = self.config().clone();
+        if !self.config_manager.reload()? {
+            return Ok(None);
+        }
+        let new = self.config();
+        let mut messages = vec![
+            Message::from(ThemeEvent::Updated),
+            Message::from(WindowManagerEvent::UpdatePlacement(new.placement())),
+            Message::from(WindowManagerEvent::UpdateIndicatorDisplay(
+                new.indicator_display(),
+            )),
+        ];
+        if let Some(name) = new
+            .preferred_output_name()
+            .filter(|n| Some(*n) != old.preferred_output_name())
+        {
+            messages.push(WindowManagerEvent::UpdatePreferredOutputName(name.clone()).into());
+        }
+        if new.manual_mode() != old.manual_mode() {
+            messages.push(ImPanelEvent::UpdateManualMode(new.manual_mode()).into());
+        }
+        if new.sticky_modifiers() != old.sticky_modifiers() {
+            messages.push(KeyboardEvent::UpdateStickyModifiers(new.sticky_modifiers()).into());
+        }
+        Ok(Some(messages))
+    }
+
     pub fn refresh(&mut self) {
         // clear temp values if needed
     }
@@ -551,6 +645,28 @@ impl ConfigState {
                 set_manual_mode,
                 |v| Message::from(ImPanelEvent::UpdateManualMode(v))
             },
+            @GestureTyping => {config_eq!(gesture_typing), set_gesture_typing},
+            @StickyModifiers => {
+                config_eq!(sticky_modifiers),
+                set_sticky_modifiers,
+                |v| Message::from(KeyboardEvent::UpdateStickyModifiers(v))
+            },
+            @WordPrediction => {
+                config_eq!(word_prediction),
+                set_word_prediction,
+                |_| Message::from(PredictEvent::Reset)
+            },
+            @ClipboardHistory => {
+                config_eq!(clipboard_history),
+                set_clipboard_history,
+                |_| Message::from(ClipboardEvent::Clear)
+            },
+            @KeyPreview => {config_eq!(key_preview), set_key_preview},
+            @CursorTrackpad => {
+                config_eq!(cursor_trackpad),
+                set_cursor_trackpad,
+                |v| Message::from(KeyboardEvent::UpdateCursorTrackpad(v))
+            },
             UpdateConfigEvent::ChangeTempText {key, init_value, value} => {
                 tracing::error!("Update temp_text[{key}] to {value}, init value[{init_value}]");
                 self.temp_texts.insert(key, (init_value, value));
@@ -657,6 +773,12 @@ pub enum UpdateConfigEvent {
         producer: fn(String) -> UpdateConfigEvent,
     },
     ManualMode(bool),
+    GestureTyping(bool),
+    StickyModifiers(bool),
+    WordPrediction(bool),
+    ClipboardHistory(bool),
+    KeyPreview(bool),
+    CursorTrackpad(bool),
 }
 
 impl From<UpdateConfigEvent> for Message {
@@ -1,6 +1,7 @@
-use std::{collections::HashMap, rc::Rc, sync::Arc};
+use std::{collections::HashMap, ops::DerefMut as _, rc::Rc, sync::Arc, time::UNIX_EPOCH};
 
 use iced::{futures::lock::Mutex as IcedFuturesMutex, Task};
+use xkeysym::Keysym;
 
 use crate::{
     app::Message,
@@ -13,11 +14,16 @@ use crate::{
     },
 };
 
+/// X11 keycodes of arrow keys
+const KEYCODE_LEFT: u32 = 113;
+const KEYCODE_RIGHT: u32 = 114;
+
 pub struct ImState {
     cur_im: Option<Rc<InputMethodInfo>>,
     ims: HashMap<String, Rc<InputMethodInfo>>,
     im_names: Vec<String>,
     candidate_area_state: CandidateAreaState,
+    preedit_state: PreeditState,
     fcitx5_services: Fcitx5Services,
 }
 
@@ -28,6 +34,7 @@ impl ImState {
             ims: Default::default(),
             im_names: Default::default(),
             candidate_area_state: Default::default(),
+            preedit_state: Default::default(),
             fcitx5_services,
         }
     }
@@ -49,13 +56,36 @@ impl ImState {
         self.candidate_area_state.update(state);
     }
 
+    pub fn update_preedit_text(&mut self, text: String) {
+        self.preedit_state.text = text;
+    }
+
+    pub fn update_preedit_caret(&mut self, caret: i32) {
+        self.preedit_state.caret = caret;
+    }
+
     fn update_ims(&mut self, ims: Vec<InputMethodInfo>) {
         tracing::debug!("New im list: {:?}", ims);
+        // keep the order of the group, so ims can be cycled in order.
+        self.im_names = ims.iter().map(|im| im.unique_name().clone()).collect();
         self.ims = ims
             .into_iter()
             .map(|im| (im.unique_name().clone(), Rc::new(im)))
             .collect();
-        self.im_names = self.ims.keys().cloned().collect();
+    }
+
+    /// The next or the previous im of the current one in the group, it wraps around.
+    pub(super) fn neighbour_im(&self, next: bool) -> Option<&String> {
+        let len = self.im_names.len();
+        let pos = self
+            .im_name()
+            .and_then(|n| self.im_names.iter().position(|i| i == n));
+        let pos = match pos {
+            Some(pos) if next => (pos + 1) % len,
+            Some(pos) => (pos + len - 1) % len,
+            None => 0,
+        };
+        self.im_names.get(pos)
     }
 
     pub(super) fn update_cur_im(&mut self, unique_name: &str) {
@@ -76,15 +106,21 @@ impl ImState {
             tracing::warn!("unable to find im: {}", unique_name);
         }
         self.candidate_area_state.reset();
+        self.preedit_state.reset();
     }
 
     pub fn candidate_area_state(&self) -> &CandidateAreaState {
         &self.candidate_area_state
     }
 
+    pub fn preedit_state(&self) -> &PreeditState {
+        &self.preedit_state
+    }
+
     fn deactivate(&mut self, im: &str) {
         if self.cur_im.take_if(|i| i.unique_name() == im).is_some() {
             self.candidate_area_state.reset();
+            self.preedit_state.reset();
         }
     }
 
@@ -96,6 +132,8 @@ impl ImState {
             }
             ImEvent::UpdateCurrentIm(im) => self.update_cur_im(&im),
             ImEvent::SelectIm(im) => return self.select_im(im),
+            // they are converted to SelectIm by State
+            ImEvent::SelectNextIm | ImEvent::SelectPrevIm => {}
             ImEvent::DeactivateIm(im) => {
                 // TODO? other logic
                 self.deactivate(&im)
@@ -114,6 +152,7 @@ impl ImState {
                 }
             }
             ImEvent::SelectCandidate(c) => return self.select_candidate(c),
+            ImEvent::MovePreeditCaret(c) => return self.move_preedit_caret(c),
         }
         Message::nothing()
     }
@@ -188,6 +227,38 @@ impl ImState {
         )
     }
 
+    /// Move the caret of the preedit to the position before the `index`th char by sending Left or
+    /// Right key events.
+    fn move_preedit_caret(&self, index: usize) -> Task<Message> {
+        let Some(caret) = self.preedit_state.caret_char_index() else {
+            return Message::nothing();
+        };
+        let (keysym, keycode, count) = if index < caret {
+            (Keysym::Left, KEYCODE_LEFT, caret - index)
+        } else {
+            (Keysym::Right, KEYCODE_RIGHT, index - caret)
+        };
+        if count == 0 {
+            return Message::nothing();
+        }
+        super::call_dbus(
+            self.fcitx5_virtual_keyboard_backend_service(),
+            format!("move preedit caret to {} failed", index),
+            |s| async move {
+                let mut s = s.lock().await;
+                let s = s.deref_mut();
+                let keyval = u32::from(keysym);
+                for _ in 0..count {
+                    // timestamp with millisecond granularity
+                    let time = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0) as u32;
+                    s.process_key_event(keyval, keycode, 0, false, time).await?;
+                    s.process_key_event(keyval, keycode, 0, true, time).await?;
+                }
+                Ok(Message::Nothing)
+            },
+        )
+    }
+
     fn prev_page(&self, page_index: i32) -> Task<Message> {
         super::call_dbus(
             self.fcitx5_virtual_keyboard_backend_service(),
@@ -315,6 +386,50 @@ impl CandidateAreaState {
     }
 }
 
+pub struct PreeditState {
+    text: String,
+    /// The byte offset of the caret in the text, it is negative if there is no caret.
+    caret: i32,
+}
+
+impl Default for PreeditState {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            caret: -1,
+        }
+    }
+}
+
+impl PreeditState {
+    fn reset(&mut self) {
+        self.text.clear();
+        self.caret = -1;
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The caret in chars.
+    pub fn caret_char_index(&self) -> Option<usize> {
+        let caret = usize::try_from(self.caret).ok()?;
+        if caret > self.text.len() {
+            return None;
+        }
+        Some(
+            self.text
+                .char_indices()
+                .take_while(|(i, _)| *i < caret)
+                .count(),
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum ImEvent {
     SyncImList,
@@ -327,6 +442,10 @@ pub enum ImEvent {
     PrevCandidates,
     NextCandidates(usize),
     SelectCandidate(usize),
+    /// Move the caret of the preedit before the char of this index.
+    MovePreeditCaret(usize),
+    SelectNextIm,
+    SelectPrevIm,
 }
 
 impl From<ImEvent> for Message {
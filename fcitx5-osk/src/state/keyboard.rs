@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    iter, mem,
     ops::DerefMut as _,
     sync::Arc,
     time::{Duration, UNIX_EPOCH},
@@ -9,22 +10,29 @@ use anyhow::Result;
 use iced::{
     alignment::{Horizontal, Vertical},
     futures::lock::Mutex as IcedFuturesMutex,
-    widget::{container::Style as ContainerStyle, text::Shaping, Column, Container, Row, Text},
-    Element, Font, Padding, Task,
+    touch::Finger as TouchFinger,
+    widget::{
+        container::Style as ContainerStyle, text::Shaping, Column, Container, Row, Stack, Text,
+    },
+    Element, Font, Padding, Point, Rectangle, Size, Task, Vector,
 };
 use xkeysym::Keysym;
 
 use crate::{
     app::Message,
+    config::Config,
     dbus::client::{
         Fcitx5Services, Fcitx5VirtualKeyboardServiceExt, IFcitx5VirtualKeyboardBackendService,
         IFcitx5VirtualKeyboardService,
     },
     font,
-    key_set::{Key, KeyValue, ThinKeyValue},
-    layout::KeyAreaLayout,
+    key_set::{ChordModifier, Key, KeyAction, KeyValue, ThinKeyValue},
+    layout::{KeyAreaLayout, KeyLabelStyle},
+    state::{CloseOpSource, ImEvent, LayoutEvent, PredictEvent, WindowManagerEvent},
     store::Store,
-    widget::{Key as KeyWidget, KeyEvent as KeyWidgetEvent, PopupKey},
+    widget::{
+        Key as KeyWidget, KeyEvent as KeyWidgetEvent, KeyHighlight, PopupKey, Swipe, SwipeEvent,
+    },
 };
 
 const TEXT_PADDING_LENGTH: u16 = 3;
@@ -34,6 +42,21 @@ const KEYCODE_LEFT_SHIFT: u32 = 50;
 
 const BORDER_RADIUS: f32 = 5.;
 
+/// A pointer should move farther than half of a normal key before it is treated as a swipe.
+const SWIPE_THRESHOLD_U: u16 = 4;
+
+/// A key should be dragged farther than this ratio of its shorter side before it is a flick.
+const FLICK_THRESHOLD_RATIO: f32 = 0.3;
+
+/// A trackpad key should be dragged farther than a normal key before it moves the cursor.
+const TRACKPAD_THRESHOLD_U: u16 = 8;
+
+/// The cursor moves one char for each step of the drag.
+const TRACKPAD_STEP_U: u16 = 3;
+
+/// A gesture should move farther than a normal key, swiping down should move twice of it.
+const GESTURE_DISTANCE_U: f32 = 8.;
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
 pub enum ModifierState {
@@ -52,11 +75,55 @@ impl ModifierState {
     pub fn is_set(&self, state: u32) -> bool {
         *self as u32 & state != 0
     }
+
+    /// If it can be latched or locked when sticky modifiers are enabled.
+    fn is_sticky(&self) -> bool {
+        matches!(
+            self,
+            ModifierState::Shift | ModifierState::Ctrl | ModifierState::Alt | ModifierState::Super
+        )
+    }
 }
 
+/// The name of a pressed key and the pointer pressing it.
+type PressedKeyId = (Arc<str>, Option<TouchFinger>);
+
 struct KeyState {
     pressed_time: u128,
     selected_key_value: ThinKeyValue,
+    finger: Option<TouchFinger>,
+    /// The key is pressed at the start of a swipe, it won't be sent when it is released.
+    swiped: bool,
+    /// The key has been sent by repeating, it won't be sent again when it is released.
+    repeated: bool,
+    /// Another key is released while this modifier is held, so it won't be latched.
+    combined: bool,
+    bounds: Rectangle,
+    /// The finger has slid to another key, that key will be sent instead.
+    slid_to: Option<SlidKey>,
+    /// The key is used as a trackpad, it won't be sent when it is released.
+    trackpad: Option<TrackpadState>,
+}
+
+struct TrackpadState {
+    /// The drag when the trackpad starts.
+    origin: Vector,
+    /// The horizontal and vertical steps sent.
+    steps: (i32, i32),
+}
+
+struct SlidKey {
+    name: Arc<str>,
+    key_value: ThinKeyValue,
+    bounds: Rectangle,
+}
+
+/// A modifier which is still active after its key is released.
+struct StickyModifier {
+    modifier: ModifierState,
+    key_value: ThinKeyValue,
+    locked: bool,
+    latched_time: u128,
 }
 
 struct HoldingKeyState {
@@ -69,6 +136,64 @@ struct HoldingKeyState {
     flags: Vec<ThinKeyValue>,
 }
 
+/// A dead key or a compose key waiting for the following keys.
+#[derive(Debug, PartialEq, Eq)]
+enum PendingCompose {
+    DeadKey(String),
+    /// The chars typed after the compose key.
+    Sequence(String),
+}
+
+/// The result of combining a key with the pending compose state.
+#[derive(Debug, PartialEq, Eq)]
+enum Composed {
+    /// The key is combined, the text is typed instead of the key.
+    Text(String),
+    /// The key is a part of a compose sequence, wait for the next key.
+    Pending(PendingCompose),
+    /// The key can't be combined, the chars consumed before are typed followed by the key.
+    Unmatched(String),
+}
+
+/// Combine the char of a key with the armed dead key or the compose sequence.
+fn combine(
+    pending_compose: PendingCompose,
+    c: Option<char>,
+    dead_keys: &HashMap<String, HashMap<char, String>>,
+    compose_sequences: &HashMap<String, String>,
+) -> Composed {
+    match pending_compose {
+        PendingCompose::DeadKey(name) => {
+            let Some(table) = dead_keys.get(&name) else {
+                return Composed::Unmatched(String::new());
+            };
+            match c.and_then(|c| table.get(&c)) {
+                Some(text) => Composed::Text(text.clone()),
+                // the standalone accent is the text of a space, it is typed before the key.
+                None => Composed::Unmatched(table.get(&' ').cloned().unwrap_or_default()),
+            }
+        }
+        PendingCompose::Sequence(sequence) => {
+            let Some(c) = c else {
+                return Composed::Unmatched(sequence);
+            };
+            let mut new_sequence = sequence.clone();
+            new_sequence.push(c);
+            if let Some(text) = compose_sequences.get(&new_sequence) {
+                Composed::Text(text.clone())
+            } else if compose_sequences
+                .keys()
+                .any(|s| s.starts_with(&new_sequence))
+            {
+                Composed::Pending(PendingCompose::Sequence(new_sequence))
+            } else {
+                tracing::debug!("compose sequence[{new_sequence}] is not found");
+                Composed::Unmatched(sequence)
+            }
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Fcitx5Hidden {
     Unset,
@@ -79,13 +204,33 @@ enum Fcitx5Hidden {
 pub struct KeyboardState {
     id: u8,
     modifiers: u32,
+    /// The armed dead key or the compose sequence being typed.
+    pending_compose: Option<PendingCompose>,
+    dead_keys: HashMap<String, HashMap<char, String>>,
+    compose_sequences: HashMap<String, String>,
+    /// The active layer of the key area layout, None means the default layer.
+    layer: Option<String>,
     primary_text_size_u: u16,
     secondary_text_size_u: u16,
+    label_style: KeyLabelStyle,
     font: Font,
     keys: HashMap<String, Key>,
-    pressed_keys: HashMap<Arc<str>, KeyState>,
+    key_bounds_u: Vec<(Arc<str>, Rectangle)>,
+    /// Keys pressed by each pointer, a key can be pressed by several fingers at the same time.
+    pressed_keys: HashMap<PressedKeyId, KeyState>,
     holding_timeout: Duration,
-    holding_key_state: Option<HoldingKeyState>,
+    /// Keys showing their secondaries, at most one for each pointer.
+    holding_key_states: Vec<HoldingKeyState>,
+    key_repeat_delay: Duration,
+    key_repeat_interval: Duration,
+    sticky_modifiers_enabled: bool,
+    double_tap_timeout: Duration,
+    sticky_modifiers: Vec<StickyModifier>,
+    cursor_trackpad: bool,
+    cursor_trackpad_vertical: bool,
+    swipe_down_to_close: bool,
+    swipe_left_to_delete_word: bool,
+    two_finger_swipe_to_switch_im: bool,
     popup_key_width_u: u16,
     popup_key_height_u: u16,
     /// if there is no indicator and fcitx5 hides virtual keyboard, we won't hide the keyboard,
@@ -97,8 +242,9 @@ pub struct KeyboardState {
 
 impl KeyboardState {
     pub fn new(
-        holding_timeout: Duration,
+        config: &Config,
         key_area_layout: &KeyAreaLayout,
+        layer: Option<&str>,
         store: &Store,
         fcitx5_services: Fcitx5Services,
     ) -> Self {
@@ -106,41 +252,115 @@ impl KeyboardState {
             id: 0,
             // always virtual
             modifiers: Default::default(),
+            pending_compose: None,
+            dead_keys: HashMap::new(),
+            compose_sequences: HashMap::new(),
+            layer: None,
             primary_text_size_u: Default::default(),
             secondary_text_size_u: Default::default(),
+            label_style: Default::default(),
             font: Default::default(),
             keys: HashMap::new(),
+            key_bounds_u: vec![],
             pressed_keys: HashMap::new(),
-            holding_timeout,
-            holding_key_state: None,
+            holding_timeout: config.holding_timeout(),
+            holding_key_states: vec![],
+            key_repeat_delay: config.key_repeat_delay(),
+            key_repeat_interval: config.key_repeat_interval(),
+            sticky_modifiers_enabled: config.sticky_modifiers(),
+            double_tap_timeout: config.double_tap_timeout(),
+            sticky_modifiers: vec![],
+            cursor_trackpad: config.cursor_trackpad(),
+            cursor_trackpad_vertical: config.cursor_trackpad_vertical(),
+            swipe_down_to_close: config.swipe_down_to_close(),
+            swipe_left_to_delete_word: config.swipe_left_to_delete_word(),
+            two_finger_swipe_to_switch_im: config.two_finger_swipe_to_switch_im(),
             popup_key_width_u: 0,
             popup_key_height_u: 0,
             fcitx5_hidden: Fcitx5Hidden::Unset,
             fcitx5_services,
         };
-        res.update_key_area_layout(key_area_layout, store);
+        res.update_key_area_layout(key_area_layout, layer, store);
         res
     }
 
-    pub fn update_key_area_layout(&mut self, key_area_layout: &KeyAreaLayout, store: &Store) {
-        self.id = self.id.wrapping_add(1);
+    /// Apply the values of a reloaded config.
+    pub fn update_config(&mut self, config: &Config) {
+        self.holding_timeout = config.holding_timeout();
+        self.key_repeat_delay = config.key_repeat_delay();
+        self.key_repeat_interval = config.key_repeat_interval();
+        self.double_tap_timeout = config.double_tap_timeout();
+        self.cursor_trackpad = config.cursor_trackpad();
+        self.cursor_trackpad_vertical = config.cursor_trackpad_vertical();
+        self.swipe_down_to_close = config.swipe_down_to_close();
+        self.swipe_left_to_delete_word = config.swipe_left_to_delete_word();
+        self.two_finger_swipe_to_switch_im = config.two_finger_swipe_to_switch_im();
+    }
+
+    pub fn update_key_area_layout(
+        &mut self,
+        key_area_layout: &KeyAreaLayout,
+        layer: Option<&str>,
+        store: &Store,
+    ) {
         self.modifiers = 0;
+        self.pending_compose = None;
         self.primary_text_size_u = key_area_layout.primary_text_size_u();
         self.secondary_text_size_u = key_area_layout.secondary_text_size_u();
+        self.label_style = key_area_layout.label_style();
         self.popup_key_width_u = key_area_layout.popup_key_width_u();
         self.popup_key_height_u = key_area_layout.popup_key_height_u();
-        self.keys = key_area_layout
-            .key_mappings()
-            .iter()
-            .filter_map(|(k, v)| store.key(v).map(|key| (k.clone(), key.clone())))
-            .collect();
-        self.pressed_keys.clear();
-        self.holding_key_state = None;
         self.font = key_area_layout
             .font()
             .as_ref()
             .map(|n| font::load(n))
             .unwrap_or_default();
+        self.update_key_area_layer(key_area_layout, layer, store);
+    }
+
+    /// Switch to another layer of the same layout, modifiers are kept.
+    pub fn update_key_area_layer(
+        &mut self,
+        key_area_layout: &KeyAreaLayout,
+        layer: Option<&str>,
+        store: &Store,
+    ) {
+        self.id = self.id.wrapping_add(1);
+        self.layer = layer.map(str::to_string);
+        let key_mappings = key_area_layout.key_mappings(layer);
+        self.keys = key_mappings
+            .iter()
+            .filter_map(|(k, v)| store.key(v).map(|key| ((*k).clone(), key.clone())))
+            .collect();
+        // the tables of all key sets used by this layer.
+        self.dead_keys.clear();
+        self.compose_sequences.clear();
+        for key_set in key_mappings.values().map(|v| store.key_set(v)) {
+            for (name, table) in key_set.dead_keys() {
+                self.dead_keys
+                    .entry(name.clone())
+                    .or_insert_with(|| table.clone());
+            }
+            for (sequence, text) in key_set.compose() {
+                self.compose_sequences
+                    .entry(sequence.clone())
+                    .or_insert_with(|| text.clone());
+            }
+        }
+        self.key_bounds_u = key_area_layout.key_bounds_u(layer);
+        self.pressed_keys.clear();
+        self.holding_key_states.clear();
+    }
+
+    pub fn layer(&self) -> Option<&str> {
+        self.layer.as_deref()
+    }
+
+    /// The modifiers of pressed keys and sticky modifiers.
+    fn modifiers(&self) -> u32 {
+        self.sticky_modifiers
+            .iter()
+            .fold(self.modifiers, |m, s| m | s.modifier as u32)
     }
 
     pub fn on_event(&mut self, event: KeyboardEvent) -> Task<Message> {
@@ -151,6 +371,71 @@ impl KeyboardState {
                 }
                 Message::nothing()
             }
+            KeyboardEvent::Swipe(id, unit, event) => {
+                if id != self.id {
+                    tracing::debug!(
+                        "receive swipe event of keyboard state id: {}, expected: {}",
+                        id,
+                        self.id
+                    );
+                    return Message::nothing();
+                }
+                match event {
+                    SwipeEvent::Started(finger) => {
+                        self.start_swipe(finger);
+                        Message::nothing()
+                    }
+                    SwipeEvent::Finished(finger, points) => {
+                        if self.is_dragging_key(finger) {
+                            return Message::nothing();
+                        }
+                        match self.recognize_gesture(unit, &points, true) {
+                            Some(task) => task,
+                            None => self.finish_swipe(unit, &points),
+                        }
+                    }
+                    SwipeEvent::Dragged(finger, points) => {
+                        if self.is_dragging_key(finger) {
+                            return Message::nothing();
+                        }
+                        match self.recognize_gesture(unit, &points, false) {
+                            Some(task) => {
+                                // the key pressed by this pointer isn't sent.
+                                self.start_swipe(finger);
+                                task
+                            }
+                            None => Message::nothing(),
+                        }
+                    }
+                    SwipeEvent::MultiFinished(paths) => {
+                        // the keys pressed by these pointers aren't sent.
+                        for (finger, _) in &paths {
+                            self.start_swipe(*finger);
+                        }
+                        let paths: Vec<_> = paths.into_iter().map(|(_, p)| p).collect();
+                        self.recognize_multi_gesture(unit, &paths)
+                            .unwrap_or_else(Message::nothing)
+                    }
+                    SwipeEvent::Moved(finger, point) => {
+                        self.slide_key(finger, unit, point);
+                        Message::nothing()
+                    }
+                }
+            }
+            KeyboardEvent::TypeText(text) => self.type_text(&text),
+            KeyboardEvent::UpdateCursorTrackpad(enabled) => {
+                self.cursor_trackpad = enabled;
+                Message::nothing()
+            }
+            KeyboardEvent::UpdateStickyModifiers(enabled) => {
+                self.sticky_modifiers_enabled = enabled;
+                if enabled {
+                    Message::nothing()
+                } else {
+                    let sticky_modifiers = mem::take(&mut self.sticky_modifiers);
+                    self.release_sticky_modifiers(sticky_modifiers)
+                }
+            }
         }
     }
 
@@ -171,14 +456,20 @@ impl KeyboardState {
             KeyEventInner::Holding(key_widget_event, pressed_time) => {
                 self.hold_key(common, key_widget_event, pressed_time);
             }
+            KeyEventInner::Repeating(finger, pressed_time) => {
+                return self.repeat_key(common, finger, pressed_time);
+            }
             KeyEventInner::Released(key_widget_event) => {
                 return self.release_key(common, key_widget_event)
             }
-            KeyEventInner::SelectSecondary => {
-                self.change_selected_secondary(common, true);
+            KeyEventInner::Moved(key_widget_event, unit) => {
+                return self.move_key(common, key_widget_event, unit)
+            }
+            KeyEventInner::SelectSecondary(finger) => {
+                self.change_selected_secondary(common, finger, true);
             }
-            KeyEventInner::UnselectSecondary => {
-                self.change_selected_secondary(common, false);
+            KeyEventInner::UnselectSecondary(finger) => {
+                self.change_selected_secondary(common, finger, false);
             }
         }
         Message::nothing()
@@ -193,6 +484,7 @@ impl KeyboardState {
     ) -> PopupKey<'a, Message> {
         let common =
             KeyEventCommon::new(self.id, holding_key_state.name.clone(), key_value.to_thin());
+        let finger = holding_key_state.key_widget_event.finger;
         PopupKey::new(
             Text::new(key_value.symbol())
                 .shaping(Shaping::Advanced)
@@ -200,13 +492,13 @@ impl KeyboardState {
                 .align_y(Vertical::Center)
                 .font(key_value.font().unwrap_or(self.font))
                 .size(self.primary_text_size_u * unit),
-            holding_key_state.key_widget_event.finger,
+            finger,
             border_radius,
         )
         .width(self.popup_key_width_u * unit)
         .height(self.popup_key_height_u * unit)
-        .on_enter(KeyEvent::new(common.clone(), KeyEventInner::SelectSecondary).into())
-        .on_exit(KeyEvent::new(common, KeyEventInner::UnselectSecondary).into())
+        .on_enter(KeyEvent::new(common.clone(), KeyEventInner::SelectSecondary(finger)).into())
+        .on_exit(KeyEvent::new(common, KeyEventInner::UnselectSecondary(finger)).into())
     }
 
     pub fn set_fcitx5_hidden(&mut self) {
@@ -214,8 +506,18 @@ impl KeyboardState {
     }
 
     #[tracing::instrument(skip(self))]
-    fn change_selected_secondary(&mut self, common: KeyEventCommon, is_select: bool) {
-        let Some(holding_key_state) = &mut self.holding_key_state else {
+    fn change_selected_secondary(
+        &mut self,
+        common: KeyEventCommon,
+        finger: Option<TouchFinger>,
+        is_select: bool,
+    ) {
+        let modifiers = self.modifiers();
+        let Some(holding_key_state) = self
+            .holding_key_states
+            .iter_mut()
+            .find(|s| s.key_widget_event.finger == finger)
+        else {
             tracing::warn!("there is no holding key");
             return;
         };
@@ -227,10 +529,11 @@ impl KeyboardState {
             return;
         }
 
-        if let Some(key_state) = self.pressed_keys.get_mut(&common.key_name) {
+        let id = (common.key_name.clone(), finger);
+        if let Some(key_state) = self.pressed_keys.get_mut(&id) {
             let key_value = common.key_value;
             if is_select {
-                key_state.selected_key_value = key_value;
+                key_state.selected_key_value = key_value.clone();
                 holding_key_state.flags.push(key_value);
             } else {
                 let mut start = 0;
@@ -246,11 +549,11 @@ impl KeyboardState {
                 holding_key_state.flags.truncate(end);
                 if key_state.selected_key_value == key_value {
                     if let Some(key_value) = holding_key_state.flags.last() {
-                        key_state.selected_key_value = *key_value;
+                        key_state.selected_key_value = key_value.clone();
                     } else {
                         // set key_value to the primary one.
-                        let is_shift_set = ModifierState::Shift.is_set(self.modifiers);
-                        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers);
+                        let is_shift_set = ModifierState::Shift.is_set(modifiers);
+                        let is_caps_lock_set = ModifierState::CapsLock.is_set(modifiers);
                         key_state.selected_key_value = holding_key_state
                             .key
                             .key_value(is_shift_set, is_caps_lock_set);
@@ -269,9 +572,11 @@ impl KeyboardState {
             height - TEXT_PADDING_LENGTH * 2,
         );
 
-        let (content, press_cb, release_cb) = if let Some(key) = self.keys.get(&*key_name) {
-            let is_shift_set = ModifierState::Shift.is_set(self.modifiers);
-            let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers);
+        let (content, highlight, press_cb, release_cb, move_cb) = if let Some(key) =
+            self.keys.get(&*key_name)
+        {
+            let is_shift_set = ModifierState::Shift.is_set(self.modifiers());
+            let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers());
             let secondary_height = inner_height / 3;
             let primary_height = inner_height - secondary_height;
             // It's related to the conversion between float and int, if we don't minus 1, it may
@@ -281,7 +586,8 @@ impl KeyboardState {
             let mut column: Column<Message> = Column::new();
             let primary_key_value = key.primary();
             let secondary_key_values = key.secondaries();
-            let (primary, secondary) = if is_shift_set ^ is_caps_lock_set {
+            let is_live_label = self.label_style == KeyLabelStyle::Live;
+            let (primary, secondary) = if is_live_label && (is_shift_set ^ is_caps_lock_set) {
                 (
                     secondary_key_values.first().unwrap_or(primary_key_value),
                     secondary_key_values.first().map(|_| primary_key_value),
@@ -331,10 +637,13 @@ impl KeyboardState {
                         .align_x(Horizontal::Center),
                 );
             }
+            let highlight = self.highlight(&key_value);
             let id = self.id;
+            let is_trackpad = self.cursor_trackpad && key.is_trackpad();
             let common = KeyEventCommon::new(id, key_name, key_value);
             (
                 Element::from(column),
+                highlight,
                 Some({
                     let common = common.clone();
                     move |key_widget_event| {
@@ -344,34 +653,139 @@ impl KeyboardState {
                         ))
                     }
                 }),
-                Some(move |key_widget_event| {
+                Some({
+                    let common = common.clone();
+                    move |key_widget_event| {
+                        Message::from(KeyEvent::new(
+                            common.clone(),
+                            KeyEventInner::Released(key_widget_event),
+                        ))
+                    }
+                }),
+                // only a trackpad key needs moves, or there will be too many messages.
+                is_trackpad.then_some(move |key_widget_event| {
                     Message::from(KeyEvent::new(
                         common.clone(),
-                        KeyEventInner::Released(key_widget_event),
+                        KeyEventInner::Moved(key_widget_event, unit),
                     ))
                 }),
             )
         } else {
             tracing::debug!("{key_name} is not found");
-            (Element::from(Text::new("")), None, None)
+            (Element::from(Text::new("")), None, None, None, None)
         };
         KeyWidget::new(content, BORDER_RADIUS)
+            .highlight(highlight)
             .on_press_with(press_cb)
             .on_release_with(release_cb)
+            .on_move_with(move_cb)
             .padding(Padding::new(TEXT_PADDING_LENGTH as f32))
             .width(width)
             .height(height)
             .into()
     }
 
-    pub fn popup_overlay(&self, unit: u16, size: (u16, u16)) -> Option<Element<Message>> {
-        const MARGIN_U: u16 = 1;
+    fn highlight(&self, key_value: &ThinKeyValue) -> Option<KeyHighlight> {
+        let armed = match (key_value.action(), &self.pending_compose) {
+            (Some(KeyAction::DeadKey(name)), Some(PendingCompose::DeadKey(armed))) => name == armed,
+            (Some(KeyAction::Compose), Some(PendingCompose::Sequence(_))) => true,
+            _ => false,
+        };
+        if armed {
+            return Some(KeyHighlight::Latched);
+        }
+        let modifier_state = to_modifier_state(key_value);
+        if modifier_state == ModifierState::CapsLock && modifier_state.is_set(self.modifiers) {
+            return Some(KeyHighlight::Locked);
+        }
+        self.sticky_modifiers
+            .iter()
+            .find(|s| s.modifier == modifier_state)
+            .map(|s| {
+                if s.locked {
+                    KeyHighlight::Locked
+                } else {
+                    KeyHighlight::Latched
+                }
+            })
+    }
+
+    /// Wrap the key area, so that a word can be typed by swiping across the keys. Slides are
+    /// tracked if swipe typing or the key preview is on, a move is reported only when the finger
+    /// enters another key.
+    pub fn swipe_area<'a>(
+        &self,
+        content: impl Into<Element<'a, Message>>,
+        unit: u16,
+        enabled: bool,
+        key_preview: bool,
+    ) -> Element<'a, Message> {
+        let id = self.id;
+        let track_moves = enabled || key_preview;
+        let move_bounds = if track_moves {
+            let unit = unit as f32;
+            self.key_bounds_u
+                .iter()
+                .map(|(_, b)| {
+                    Rectangle::new(
+                        Point::new(b.x * unit, b.y * unit),
+                        Size::new(b.width * unit, b.height * unit),
+                    )
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        Swipe::new(
+            content,
+            move |event| Message::from(KeyboardEvent::Swipe(id, unit, event)),
+            enabled,
+        )
+        .threshold((SWIPE_THRESHOLD_U * unit) as f32)
+        .track_moves(track_moves)
+        .move_bounds(move_bounds)
+        .gestures(
+            self.swipe_down_to_close
+                || self.swipe_left_to_delete_word
+                || self.two_finger_swipe_to_switch_im,
+        )
+        .into()
+    }
+
+    pub fn popup_overlay(
+        &self,
+        unit: u16,
+        size: (u16, u16),
+        key_preview: bool,
+    ) -> Option<Element<Message>> {
+        if self.holding_key_states.is_empty() {
+            return key_preview.then(|| self.key_preview(unit, size)).flatten();
+        }
         let (width, height) = size;
+        Some(
+            Stack::with_children(
+                self.holding_key_states
+                    .iter()
+                    .map(|s| self.holding_popup(s, unit, size)),
+            )
+            .width(width)
+            .height(height)
+            .into(),
+        )
+    }
 
-        let holding_key_state = self.holding_key_state.as_ref()?;
+    /// The secondaries of a holding key above it.
+    fn holding_popup<'a>(
+        &'a self,
+        holding_key_state: &'a HoldingKeyState,
+        unit: u16,
+        size: (u16, u16),
+    ) -> Element<'a, Message> {
+        const MARGIN_U: u16 = 1;
+        let (width, height) = size;
 
-        let is_shift_set = ModifierState::Shift.is_set(self.modifiers);
-        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers);
+        let is_shift_set = ModifierState::Shift.is_set(self.modifiers());
+        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers());
 
         let key = &holding_key_state.key;
         let mut row = Row::new();
@@ -402,14 +816,79 @@ impl KeyboardState {
         }
 
         // calculate padding.
+        let padding = Padding::default().left(left_x as f32).top(top_y as f32);
+        Container::new(Container::new(row).style(|theme| {
+            let mut style = ContainerStyle::default();
+            style.shadow.offset = [1.0, 1.0].into();
+            style.shadow.color = theme.extended_palette().background.weak.color;
+            style.shadow.blur_radius = 5.;
+            style.border = style.border.rounded(5);
+            style.background = Some(theme.extended_palette().primary.weak.color.into());
+            style
+        }))
+        .padding(padding)
+        .width(width)
+        .height(height)
+        .into()
+    }
+
+    /// An enlarged label of the latest pressed key above it.
+    fn key_preview(&self, unit: u16, size: (u16, u16)) -> Option<Element<Message>> {
+        const MARGIN_U: u16 = 1;
+        let (width, height) = size;
+
+        let ((name, _), key_state) = self
+            .pressed_keys
+            .iter()
+            .filter(|(_, s)| {
+                !s.swiped
+                    && s.trackpad.is_none()
+                    && s.selected_key_value.action().is_none()
+                    && to_modifier_state(&s.selected_key_value) == ModifierState::NoState
+            })
+            .max_by_key(|(_, s)| s.pressed_time)?;
+        let (name, bounds) = match &key_state.slid_to {
+            Some(slid_key) => (&slid_key.name, slid_key.bounds),
+            None => (name, key_state.bounds),
+        };
+        let is_shift_set = ModifierState::Shift.is_set(self.modifiers());
+        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers());
+        let key_value = self
+            .keys
+            .get(&**name)?
+            .full_key_value(is_shift_set, is_caps_lock_set);
+
+        let preview_width = (bounds.width as u16).max(self.popup_key_width_u * unit);
+        let preview_height = self.popup_key_height_u * unit;
+        let text: Text<'_> = Text::new(key_value.symbol())
+            .shaping(Shaping::Advanced)
+            .font(key_value.font().unwrap_or(self.font))
+            .size(self.primary_text_size_u * unit * 3 / 2)
+            .width(preview_width)
+            .height(preview_height)
+            .align_x(Horizontal::Center)
+            .align_y(Vertical::Center);
+
+        // calculate position, it is centered above the key.
+        let center_x = (bounds.x + bounds.width / 2.) as u16;
+        let left_x = center_x
+            .saturating_sub(preview_width / 2)
+            .min(width.saturating_sub(preview_width));
+        let mut top_y = bounds.y as u16;
+        if top_y > preview_height + MARGIN_U * unit {
+            top_y -= preview_height + MARGIN_U * unit;
+        } else {
+            top_y += bounds.height as u16 + MARGIN_U * unit;
+        }
+
         let padding = Padding::default().left(left_x as f32).top(top_y as f32);
         Some(
-            Container::new(Container::new(row).style(|theme| {
+            Container::new(Container::new(text).style(|theme| {
                 let mut style = ContainerStyle::default();
                 style.shadow.offset = [1.0, 1.0].into();
                 style.shadow.color = theme.extended_palette().background.weak.color;
                 style.shadow.blur_radius = 5.;
-                style.border = style.border.rounded(5);
+                style.border = style.border.rounded(BORDER_RADIUS);
                 style.background = Some(theme.extended_palette().primary.weak.color.into());
                 style
             }))
@@ -419,6 +898,76 @@ impl KeyboardState {
             .into(),
         )
     }
+
+    /// Track the key under a pressed finger, so the finger can slide to the key it means to hit.
+    fn slide_key(&mut self, finger: Option<TouchFinger>, unit: u16, point: Point) {
+        let is_shift_set = ModifierState::Shift.is_set(self.modifiers());
+        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers());
+        let Some(((name, _), key_state)) = self
+            .pressed_keys
+            .iter_mut()
+            .find(|(_, s)| s.finger == finger && !s.swiped && !s.repeated)
+        else {
+            return;
+        };
+        // only normal keys can slide, a holding key selects its secondaries by the popup.
+        let is_normal = |key_value: &ThinKeyValue| {
+            key_value.action().is_none() && to_modifier_state(key_value) == ModifierState::NoState
+        };
+        if !is_normal(&key_state.selected_key_value)
+            || self
+                .holding_key_states
+                .iter()
+                .any(|s| s.key_widget_event.finger == finger)
+            || self.keys.get(&**name).is_some_and(|k| {
+                k.is_repeatable() || k.has_flick() || (self.cursor_trackpad && k.is_trackpad())
+            })
+        {
+            return;
+        }
+
+        let unit = unit as f32;
+        let point_u = Point::new(point.x / unit, point.y / unit);
+        let target = self.key_bounds_u.iter().find(|(_, b)| b.contains(point_u));
+        let Some((target_name, target_bounds_u)) = target.filter(|(n, _)| n != name) else {
+            // back to the pressed key or out of the key area.
+            key_state.slid_to = None;
+            return;
+        };
+        if key_state
+            .slid_to
+            .as_ref()
+            .is_some_and(|s| s.name == *target_name)
+        {
+            return;
+        }
+        let key_value = self
+            .keys
+            .get(&**target_name)
+            .map(|k| k.key_value(is_shift_set, is_caps_lock_set))
+            .filter(is_normal);
+        let pressed_bounds_u = self
+            .key_bounds_u
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, b)| b);
+        let (Some(key_value), Some(pressed_bounds_u)) = (key_value, pressed_bounds_u) else {
+            key_state.slid_to = None;
+            return;
+        };
+        // key_bounds_u is relative to the key area, but the bounds of key widgets are not.
+        let offset = key_state.bounds.position()
+            - Point::new(pressed_bounds_u.x * unit, pressed_bounds_u.y * unit);
+        let bounds = Rectangle::new(
+            Point::new(target_bounds_u.x * unit, target_bounds_u.y * unit) + offset,
+            Size::new(target_bounds_u.width * unit, target_bounds_u.height * unit),
+        );
+        key_state.slid_to = Some(SlidKey {
+            name: target_name.clone(),
+            key_value,
+            bounds,
+        });
+    }
 }
 
 // call fcitx5
@@ -442,12 +991,20 @@ impl KeyboardState {
         common: KeyEventCommon,
         key_widget_event: KeyWidgetEvent,
     ) -> Task<Message> {
-        let modifier_state = to_modifier_state(common.key_value);
+        let modifier_state = to_modifier_state(&common.key_value);
         if modifier_state != ModifierState::CapsLock {
             self.modifiers |= modifier_state as u32;
         }
+        // the pressed event of a modifier key is sent by the first finger pressing it.
+        let is_modifier_held = modifier_state != ModifierState::NoState
+            && self
+                .pressed_keys
+                .keys()
+                .any(|(name, _)| *name == common.key_name);
 
-        let key_state = self.pressed_keys.entry(common.key_name.clone());
+        let key_state = self
+            .pressed_keys
+            .entry((common.key_name.clone(), key_widget_event.finger));
         let mut contains = true;
         let pressed_time = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0);
         {
@@ -456,12 +1013,43 @@ impl KeyboardState {
                 *contains = false;
                 KeyState {
                     pressed_time,
-                    selected_key_value: common.key_value,
+                    selected_key_value: common.key_value.clone(),
+                    finger: key_widget_event.finger,
+                    swiped: false,
+                    repeated: false,
+                    combined: false,
+                    bounds: key_widget_event.bounds,
+                    slid_to: None,
+                    trackpad: None,
                 }
             });
         }
+        let is_sticky = self
+            .sticky_modifiers
+            .iter()
+            .any(|s| s.modifier == modifier_state);
         let mut task = self.clear_fcitx5_hidden();
-        if modifier_state == ModifierState::NoState && !contains {
+        if is_modifier_held {
+            return task;
+        }
+        // a trackpad key isn't repeated, otherwise it repeats before the finger moves far enough.
+        let is_repeatable = self
+            .keys
+            .get(&*common.key_name)
+            .filter(|k| k.is_repeatable() && !(self.cursor_trackpad && k.is_trackpad()))
+            .is_some();
+        if modifier_state == ModifierState::NoState && !contains && is_repeatable {
+            let key_repeat_delay = self.key_repeat_delay;
+            let next = Task::future(async move {
+                tokio::time::sleep(key_repeat_delay).await;
+                KeyEvent::new(
+                    common,
+                    KeyEventInner::Repeating(key_widget_event.finger, pressed_time),
+                )
+                .into()
+            });
+            task = task.chain(next);
+        } else if modifier_state == ModifierState::NoState && !contains {
             let holding_timeout = self.holding_timeout;
             let next = Task::future(async move {
                 tokio::time::sleep(holding_timeout).await;
@@ -474,10 +1062,13 @@ impl KeyboardState {
             task = task.chain(next);
         } else if modifier_state != ModifierState::CapsLock
             && modifier_state != ModifierState::Shift
+            && !is_sticky
         {
-            // Don't send caps lock and shift state.
-            let modifiers =
-                self.modifiers & !(ModifierState::CapsLock as u32) & !(ModifierState::Shift as u32);
+            // Don't send caps lock and shift state. And the pressed event of a sticky modifier
+            // has been sent.
+            let modifiers = self.modifiers()
+                & !(ModifierState::CapsLock as u32)
+                & !(ModifierState::Shift as u32);
             let next = super::call_dbus(
                 self.fcitx5_virtual_keyboard_backend_service(),
                 format!("send key pressed event failed: {}", common.key_name),
@@ -515,18 +1106,88 @@ impl KeyboardState {
         common: KeyEventCommon,
         key_widget_event: KeyWidgetEvent,
     ) -> Task<Message> {
-        let modifier_state = to_modifier_state(common.key_value);
+        let modifier_state = to_modifier_state(&common.key_value);
+        let finger = key_widget_event.finger;
+        let key_state = self.pressed_keys.remove(&(common.key_name.clone(), finger));
+        if modifier_state != ModifierState::NoState
+            && self
+                .pressed_keys
+                .keys()
+                .any(|(name, _)| *name == common.key_name)
+        {
+            // another finger still holds this modifier key, it is released by the last finger.
+            return Message::nothing();
+        }
         match modifier_state {
             s @ ModifierState::CapsLock => self.modifiers ^= s as u32,
+            // the modifier is still held by another key, e.g. the other shift key.
+            s if self
+                .pressed_keys
+                .values()
+                .any(|k| to_modifier_state(&k.selected_key_value) == s) => {}
             s => self.modifiers &= !(s as u32),
         };
 
-        if let Some(key_state) = self.pressed_keys.remove(&common.key_name) {
-            self.holding_key_state
-                .take_if(|s| s.name == common.key_name);
+        if let Some(mut key_state) = key_state {
+            let holding = self
+                .holding_key_states
+                .iter()
+                .position(|s| s.key_widget_event.finger == finger && s.name == common.key_name)
+                .map(|i| self.holding_key_states.remove(i))
+                .is_some();
+            if let Some(slid_key) = key_state.slid_to.take() {
+                key_state.selected_key_value = slid_key.key_value;
+            }
+            // a secondary selected from the popup wins.
+            if !holding && !key_widget_event.cancelled {
+                if let Some(key_value) = self.flick_key_value(&common.key_name, &key_widget_event) {
+                    key_state.selected_key_value = key_value;
+                }
+            }
 
             let pressed_time = key_state.pressed_time;
             let released_time = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0);
+            let cancelled = key_widget_event.cancelled
+                || key_state.swiped
+                || key_state.repeated
+                || key_state.trackpad.is_some();
+
+            if let Some(action) = key_state.selected_key_value.action() {
+                if cancelled {
+                    return Message::nothing();
+                }
+                let pending_compose = match action {
+                    KeyAction::DeadKey(name) => PendingCompose::DeadKey(name.clone()),
+                    KeyAction::Compose => PendingCompose::Sequence(String::new()),
+                    action => return Task::done(on_key_action(action)),
+                };
+                // tapping an armed key again disarms it.
+                if self.pending_compose.take().as_ref() != Some(&pending_compose) {
+                    self.pending_compose = Some(pending_compose);
+                }
+                return Message::nothing();
+            }
+
+            if modifier_state.is_sticky() {
+                if let Some(task) = self.update_sticky_modifier(
+                    modifier_state,
+                    &key_state,
+                    released_time,
+                    cancelled,
+                ) {
+                    return task;
+                }
+            }
+
+            // latched modifiers are applied to this key, release them after this key.
+            let mut latched_modifiers = vec![];
+            if modifier_state == ModifierState::NoState && (!cancelled || key_state.repeated) {
+                self.pressed_keys
+                    .values_mut()
+                    .filter(|s| to_modifier_state(&s.selected_key_value) != ModifierState::NoState)
+                    .for_each(|s| s.combined = true);
+                latched_modifiers = self.take_latched_modifiers();
+            }
 
             if modifier_state == ModifierState::CapsLock
                 || (modifier_state == ModifierState::Shift && released_time - pressed_time > 500)
@@ -538,42 +1199,227 @@ impl KeyboardState {
             }
 
             // not send caps lock and shift state.
-            let modifiers =
-                self.modifiers & !(ModifierState::CapsLock as u32) & !(ModifierState::Shift as u32);
+            let modifiers = self.modifiers()
+                & !(ModifierState::CapsLock as u32)
+                & !(ModifierState::Shift as u32);
+            // a shortcut isn't a part of a word, and it isn't composed.
+            let is_plain = modifier_state == ModifierState::NoState
+                && !cancelled
+                && modifiers == 0
+                && key_state.selected_key_value.modifiers().is_empty();
+            let mut unmatched = Message::nothing();
+            if is_plain {
+                match self.compose(key_state.selected_key_value.keysym()) {
+                    Ok(task) => {
+                        return task.chain(self.release_sticky_modifiers(latched_modifiers));
+                    }
+                    Err(task) => unmatched = task,
+                }
+            }
+            let typed = if is_plain {
+                key_typed(key_state.selected_key_value.keysym())
+            } else {
+                Message::nothing()
+            };
 
-            super::call_dbus(
-                self.fcitx5_virtual_keyboard_backend_service(),
-                format!(
-                    "send key pressed/released event failed: {}",
-                    common.key_name
-                ),
-                |s| async move {
-                    let mut s = s.lock().await;
+            unmatched
+                .chain(super::call_dbus(
+                    self.fcitx5_virtual_keyboard_backend_service(),
+                    format!(
+                        "send key pressed/released event failed: {}",
+                        common.key_name
+                    ),
+                    |s| async move {
+                        let mut s = s.lock().await;
 
-                    on_key_release(
-                        s.deref_mut(),
-                        &key_state,
-                        modifier_state,
-                        modifiers,
-                        pressed_time,
-                        released_time,
-                        key_widget_event.cancelled,
-                    )
-                    .await
-                },
-            )
+                        on_key_release(
+                            s.deref_mut(),
+                            &key_state,
+                            modifier_state,
+                            modifiers,
+                            pressed_time,
+                            released_time,
+                            cancelled,
+                        )
+                        .await
+                    },
+                ))
+                .chain(self.release_sticky_modifiers(latched_modifiers))
+                .chain(typed)
         } else {
             Message::nothing()
         }
     }
 
+    /// Combine a key with the armed dead key or the compose sequence. Return Err if the key
+    /// should be sent as usual, the task of it types the chars consumed by the pending state.
+    fn compose(&mut self, keysym: Keysym) -> Result<Task<Message>, Task<Message>> {
+        let Some(pending_compose) = self.pending_compose.take() else {
+            return Err(Message::nothing());
+        };
+        match combine(
+            pending_compose,
+            keysym.key_char(),
+            &self.dead_keys,
+            &self.compose_sequences,
+        ) {
+            Composed::Text(text) => Ok(self.type_text(&text)),
+            Composed::Pending(pending_compose) => {
+                self.pending_compose = Some(pending_compose);
+                Ok(Message::nothing())
+            }
+            Composed::Unmatched(consumed) if consumed.is_empty() => Err(Message::nothing()),
+            Composed::Unmatched(consumed) => Err(self.type_text(&consumed)),
+        }
+    }
+
+    /// Turn a long drag on a trackpad key into arrow keys, one for each step of the drag.
+    fn move_key(
+        &mut self,
+        common: KeyEventCommon,
+        key_widget_event: KeyWidgetEvent,
+        unit: u16,
+    ) -> Task<Message> {
+        if !self.cursor_trackpad {
+            return Message::nothing();
+        }
+        let vertical = self.cursor_trackpad_vertical;
+        let finger = key_widget_event.finger;
+        let Some(key_state) = self
+            .pressed_keys
+            .get_mut(&(common.key_name.clone(), finger))
+            .filter(|s| !s.swiped && !s.repeated)
+        else {
+            return Message::nothing();
+        };
+        let drag = key_widget_event.drag;
+        let trackpad = match &mut key_state.trackpad {
+            Some(trackpad) => trackpad,
+            None => {
+                let distance = if vertical {
+                    drag.x.hypot(drag.y)
+                } else {
+                    drag.x.abs()
+                };
+                if distance <= (TRACKPAD_THRESHOLD_U * unit) as f32 {
+                    return Message::nothing();
+                }
+                // the key won't be sent, neither its popup.
+                self.holding_key_states
+                    .retain(|s| s.key_widget_event.finger != finger);
+                key_state.trackpad.insert(TrackpadState {
+                    origin: drag,
+                    steps: (0, 0),
+                })
+            }
+        };
+        let step = (TRACKPAD_STEP_U * unit) as f32;
+        let x = ((drag.x - trackpad.origin.x) / step) as i32;
+        let y = if vertical {
+            ((drag.y - trackpad.origin.y) / step) as i32
+        } else {
+            0
+        };
+        let (dx, dy) = (x - trackpad.steps.0, y - trackpad.steps.1);
+        trackpad.steps = (x, y);
+
+        let horizontal_keysym = if dx > 0 { Keysym::Right } else { Keysym::Left };
+        let vertical_keysym = if dy > 0 { Keysym::Down } else { Keysym::Up };
+        let key_values: Vec<_> = iter::repeat_n(horizontal_keysym, dx.unsigned_abs() as usize)
+            .chain(iter::repeat_n(vertical_keysym, dy.unsigned_abs() as usize))
+            .map(|keysym| self.find_key_value(keysym))
+            .collect();
+        if key_values.is_empty() {
+            return Message::nothing();
+        }
+        // the word being typed is left.
+        self.send_key_values(key_values, "send arrow keys of the trackpad failed")
+            .chain(Task::done(PredictEvent::Reset.into()))
+    }
+
+    /// Latch, lock or unlock a sticky modifier when its key is released. Return None if the key
+    /// should be released as a normal modifier.
+    fn update_sticky_modifier(
+        &mut self,
+        modifier_state: ModifierState,
+        key_state: &KeyState,
+        released_time: u128,
+        cancelled: bool,
+    ) -> Option<Task<Message>> {
+        let index = self
+            .sticky_modifiers
+            .iter()
+            .position(|s| s.modifier == modifier_state);
+        if let Some(index) = index {
+            let sticky_modifier = &mut self.sticky_modifiers[index];
+            if !cancelled
+                && !sticky_modifier.locked
+                && !key_state.combined
+                && released_time - sticky_modifier.latched_time
+                    <= self.double_tap_timeout.as_millis()
+            {
+                sticky_modifier.locked = true;
+                return Some(Message::nothing());
+            }
+            // unlatch or unlock
+            let sticky_modifier = self.sticky_modifiers.remove(index);
+            Some(self.release_sticky_modifiers(vec![sticky_modifier]))
+        } else if self.sticky_modifiers_enabled && !cancelled && !key_state.combined {
+            self.sticky_modifiers.push(StickyModifier {
+                modifier: modifier_state,
+                key_value: key_state.selected_key_value.clone(),
+                locked: false,
+                latched_time: released_time,
+            });
+            Some(Message::nothing())
+        } else {
+            None
+        }
+    }
+
+    fn take_latched_modifiers(&mut self) -> Vec<StickyModifier> {
+        let (locked, latched) = mem::take(&mut self.sticky_modifiers)
+            .into_iter()
+            .partition(|s| s.locked);
+        self.sticky_modifiers = locked;
+        latched
+    }
+
+    /// Send released events of sticky modifiers, shift is never sent.
+    fn release_sticky_modifiers(&self, sticky_modifiers: Vec<StickyModifier>) -> Task<Message> {
+        let key_values: Vec<_> = sticky_modifiers
+            .into_iter()
+            .filter(|s| s.modifier != ModifierState::Shift)
+            .map(|s| s.key_value)
+            .collect();
+        if key_values.is_empty() {
+            return Message::nothing();
+        }
+        let released_time = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0);
+        super::call_dbus(
+            self.fcitx5_virtual_keyboard_backend_service(),
+            "send released events of sticky modifiers failed",
+            |s| async move {
+                let mut s = s.lock().await;
+                for key_value in key_values {
+                    let keyval = u32::from(key_value.keysym());
+                    let keycode = key_value.keycode().unwrap_or(0).unsigned_abs() as u32;
+                    s.process_key_event(keyval, keycode, 0, true, released_time as u32)
+                        .await?;
+                }
+                Ok(Message::Nothing)
+            },
+        )
+    }
+
     fn hold_key(
         &mut self,
         common: KeyEventCommon,
         key_widget_event: KeyWidgetEvent,
         pressed_time: u128,
     ) {
-        if let Some(key_state) = self.pressed_keys.get(&common.key_name) {
+        let finger = key_widget_event.finger;
+        if let Some(key_state) = self.pressed_keys.get(&(common.key_name.clone(), finger)) {
             // check if the pressed time is the same
             if key_state.pressed_time != pressed_time {
                 tracing::debug!(
@@ -583,13 +1429,21 @@ impl KeyboardState {
                 );
                 return;
             }
+            if key_state.swiped || key_state.slid_to.is_some() || key_state.trackpad.is_some() {
+                return;
+            }
         } else {
             return;
         }
 
-        if let Some(holding_key_state) = &self.holding_key_state {
+        if let Some(holding_key_state) = self
+            .holding_key_states
+            .iter()
+            .find(|s| s.key_widget_event.finger == finger)
+        {
             tracing::warn!(
-                "it can't be holding two keys at the same time, {} is already holding, holding {} will be skipped",
+                "finger {:?} is already holding {}, holding {} will be skipped",
+                finger,
                 holding_key_state.name,
                 common.key_name
             );
@@ -598,7 +1452,7 @@ impl KeyboardState {
 
         if let Some(key) = self.keys.get(&*common.key_name) {
             if key.has_secondary() {
-                self.holding_key_state = Some(HoldingKeyState {
+                self.holding_key_states.push(HoldingKeyState {
                     name: common.key_name,
                     key_widget_event,
                     key: key.clone(),
@@ -608,6 +1462,291 @@ impl KeyboardState {
         }
     }
 
+    fn repeat_key(
+        &mut self,
+        common: KeyEventCommon,
+        finger: Option<TouchFinger>,
+        pressed_time: u128,
+    ) -> Task<Message> {
+        let Some(key_state) = self
+            .pressed_keys
+            .get_mut(&(common.key_name.clone(), finger))
+            .filter(|s| s.pressed_time == pressed_time && !s.swiped && s.trackpad.is_none())
+        else {
+            // the key is released or pressed again, stop repeating.
+            return Message::nothing();
+        };
+        key_state.repeated = true;
+        let key_value = key_state.selected_key_value.clone();
+        let typed = if key_value.modifiers().is_empty() {
+            key_typed(key_value.keysym())
+        } else {
+            Message::nothing()
+        };
+
+        let now = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0);
+        let send = super::call_dbus(
+            self.fcitx5_virtual_keyboard_backend_service(),
+            format!("send repeated key event failed: {}", common.key_name),
+            |s| async move {
+                let mut s = s.lock().await;
+                let key_state = KeyState {
+                    pressed_time: now,
+                    selected_key_value: key_value,
+                    finger: None,
+                    swiped: false,
+                    repeated: false,
+                    combined: false,
+                    bounds: Rectangle::default(),
+                    slid_to: None,
+                    trackpad: None,
+                };
+                on_key_release(
+                    s.deref_mut(),
+                    &key_state,
+                    ModifierState::NoState,
+                    0,
+                    now,
+                    now + 1,
+                    false,
+                )
+                .await
+            },
+        );
+        let key_repeat_interval = self.key_repeat_interval;
+        let next = Task::future(async move {
+            tokio::time::sleep(key_repeat_interval).await;
+            KeyEvent::new(common, KeyEventInner::Repeating(finger, pressed_time)).into()
+        });
+        send.chain(typed).chain(next)
+    }
+
+    /// The value of a key flicked to a direction, None if it isn't a flick.
+    fn flick_key_value(
+        &self,
+        key_name: &str,
+        key_widget_event: &KeyWidgetEvent,
+    ) -> Option<ThinKeyValue> {
+        let (direction, distance) = key_widget_event.drag_direction()?;
+        let bounds = key_widget_event.bounds;
+        if distance <= bounds.width.min(bounds.height) * FLICK_THRESHOLD_RATIO {
+            return None;
+        }
+        self.keys
+            .get(key_name)?
+            .flick(direction)
+            .map(KeyValue::to_thin)
+    }
+
+    /// Recognize the gesture of a pointer, return None if it isn't a gesture. A path of gesture
+    /// typing isn't recognized as swiping down, it may be a word typed downward.
+    fn recognize_gesture(
+        &self,
+        unit: u16,
+        points: &[Point],
+        swipe_typing: bool,
+    ) -> Option<Task<Message>> {
+        let (first, last) = (points.first()?, points.last()?);
+        let unit = unit as f32;
+        let (dx, dy) = ((last.x - first.x) / unit, (last.y - first.y) / unit);
+        if self.swipe_down_to_close
+            && !swipe_typing
+            && dy > GESTURE_DISTANCE_U * 2.
+            && dy > dx.abs() * 2.
+        {
+            return Some(Task::done(
+                WindowManagerEvent::CloseKeyboard(CloseOpSource::UserAction).into(),
+            ));
+        }
+        if self.swipe_left_to_delete_word && -dx > GESTURE_DISTANCE_U && -dx > dy.abs() * 2. {
+            let first_u = Point::new(first.x / unit, first.y / unit);
+            let from_backspace = self
+                .key_bounds_u
+                .iter()
+                .find(|(_, b)| b.contains(first_u))
+                .and_then(|(name, _)| self.keys.get(&**name))
+                .is_some_and(|k| k.primary().keysym() == Keysym::BackSpace);
+            if from_backspace {
+                let key_value = self
+                    .find_key_value(Keysym::BackSpace)
+                    .with_modifiers(vec![ChordModifier::Ctrl]);
+                return Some(
+                    self.send_key_values(vec![key_value], "send Ctrl+BackSpace failed")
+                        .chain(Task::done(PredictEvent::Reset.into())),
+                );
+            }
+        }
+        None
+    }
+
+    /// Recognize the gesture of several pointers, return None if it isn't a gesture.
+    fn recognize_multi_gesture(&self, unit: u16, paths: &[Vec<Point>]) -> Option<Task<Message>> {
+        if !self.two_finger_swipe_to_switch_im || paths.len() != 2 {
+            return None;
+        }
+        let unit = unit as f32;
+        let mut directions = paths.iter().map(|points| {
+            let (first, last) = (points.first()?, points.last()?);
+            let (dx, dy) = ((last.x - first.x) / unit, (last.y - first.y) / unit);
+            (dx.abs() > GESTURE_DISTANCE_U && dx.abs() > dy.abs() * 2.).then_some(dx > 0.)
+        });
+        let to_right = directions.next().flatten()?;
+        if directions.next().flatten()? != to_right {
+            return None;
+        }
+        let event = if to_right {
+            ImEvent::SelectPrevIm
+        } else {
+            ImEvent::SelectNextIm
+        };
+        Some(Task::done(event.into()))
+    }
+
+    /// A key with flicks or a trackpad key is pressed by the pointer, so its moving isn't a
+    /// swipe.
+    fn is_dragging_key(&self, finger: Option<TouchFinger>) -> bool {
+        self.pressed_keys.iter().any(|((name, _), s)| {
+            s.finger == finger
+                && self
+                    .keys
+                    .get(&**name)
+                    .is_some_and(|k| k.has_flick() || (self.cursor_trackpad && k.is_trackpad()))
+        })
+    }
+
+    fn start_swipe(&mut self, finger: Option<TouchFinger>) {
+        if self.is_dragging_key(finger) {
+            return;
+        }
+        self.pressed_keys
+            .values_mut()
+            .filter(|s| s.finger == finger)
+            .for_each(|s| s.swiped = true);
+        self.holding_key_states
+            .retain(|s| s.key_widget_event.finger != finger);
+    }
+
+    /// Turn the path of a swipe into a key sequence, only keys of letters will be sent. The input
+    /// method can pick a word from the sequence.
+    fn finish_swipe(&mut self, unit: u16, points: &[Point]) -> Task<Message> {
+        let is_shift_set = ModifierState::Shift.is_set(self.modifiers());
+        let is_caps_lock_set = ModifierState::CapsLock.is_set(self.modifiers());
+        let unit = unit as f32;
+        let mut key_values = vec![];
+        let mut last_name = None;
+        for point in points {
+            let point = Point::new(point.x / unit, point.y / unit);
+            let Some((name, _)) = self.key_bounds_u.iter().find(|(_, b)| b.contains(point)) else {
+                continue;
+            };
+            if last_name == Some(name) {
+                continue;
+            }
+            last_name = Some(name);
+            let Some(key) = self.keys.get(&**name) else {
+                continue;
+            };
+            let key_value = key.key_value(is_shift_set, is_caps_lock_set);
+            // a shortcut isn't a part of a word.
+            if key_value.modifiers().is_empty()
+                && key_value
+                    .keysym()
+                    .key_char()
+                    .filter(|c| c.is_alphabetic())
+                    .is_some()
+            {
+                key_values.push(key_value);
+            }
+        }
+        tracing::debug!("swiped keys: {:?}", key_values);
+        if key_values.is_empty() {
+            return Message::nothing();
+        }
+        let latched_modifiers = self.take_latched_modifiers();
+        let typed = key_values
+            .iter()
+            .map(|v| key_typed(v.keysym()))
+            .fold(Message::nothing(), Task::chain);
+
+        self.clear_fcitx5_hidden()
+            .chain(self.send_key_values(key_values, "send swiped keys failed"))
+            .chain(self.release_sticky_modifiers(latched_modifiers))
+            .chain(typed)
+    }
+
+    /// Type a text by sending the keys of its chars. The value of a key in the layout is used if
+    /// the char is found, so that its keycode can be sent.
+    fn type_text(&mut self, text: &str) -> Task<Message> {
+        let key_values: Vec<_> = text
+            .chars()
+            .filter(|c| *c != '\r')
+            .map(|c| {
+                let keysym = match c {
+                    '\n' => Keysym::Return,
+                    '\t' => Keysym::Tab,
+                    c => Keysym::from_char(c),
+                };
+                self.find_key_value(keysym)
+            })
+            .collect();
+        if key_values.is_empty() {
+            return Message::nothing();
+        }
+        self.clear_fcitx5_hidden()
+            .chain(self.send_key_values(key_values, format!("type text failed: {text}")))
+    }
+
+    /// The value of a key in the layout if it is found, so that its keycode can be sent.
+    fn find_key_value(&self, keysym: Keysym) -> ThinKeyValue {
+        self.keys
+            .values()
+            .flat_map(|k| iter::once(k.primary()).chain(k.secondaries()))
+            .find(|v| v.keysym() == keysym && v.action().is_none() && v.modifiers().is_empty())
+            .map(KeyValue::to_thin)
+            .unwrap_or_else(|| ThinKeyValue::new(keysym))
+    }
+
+    /// Send pressed and released events of keys one by one.
+    fn send_key_values(
+        &self,
+        key_values: Vec<ThinKeyValue>,
+        err_msg: impl Into<String>,
+    ) -> Task<Message> {
+        let start_time = UNIX_EPOCH.elapsed().map(|d| d.as_millis()).unwrap_or(0);
+        super::call_dbus(
+            self.fcitx5_virtual_keyboard_backend_service(),
+            err_msg,
+            |s| async move {
+                let mut s = s.lock().await;
+                for (i, key_value) in key_values.into_iter().enumerate() {
+                    let pressed_time = start_time + 2 * i as u128;
+                    let key_state = KeyState {
+                        pressed_time,
+                        selected_key_value: key_value,
+                        finger: None,
+                        swiped: false,
+                        repeated: false,
+                        combined: false,
+                        bounds: Rectangle::default(),
+                        slid_to: None,
+                        trackpad: None,
+                    };
+                    on_key_release(
+                        s.deref_mut(),
+                        &key_state,
+                        ModifierState::NoState,
+                        0,
+                        pressed_time,
+                        pressed_time + 1,
+                        false,
+                    )
+                    .await?;
+                }
+                Ok(Message::Nothing)
+            },
+        )
+    }
+
     pub fn clear_fcitx5_hidden(&mut self) -> Task<Message> {
         if self.fcitx5_hidden != Fcitx5Hidden::Unset {
             // make sure to unset the flag.
@@ -659,9 +1798,13 @@ impl KeyEventCommon {
 enum KeyEventInner {
     Pressed(KeyWidgetEvent),
     Holding(KeyWidgetEvent, u128),
+    /// The pointer and the pressed time of the repeating key.
+    Repeating(Option<TouchFinger>, u128),
     Released(KeyWidgetEvent),
-    SelectSecondary,
-    UnselectSecondary,
+    /// A pressed pointer moves, and the unit.
+    Moved(KeyWidgetEvent, u16),
+    SelectSecondary(Option<TouchFinger>),
+    UnselectSecondary(Option<TouchFinger>),
 }
 
 impl From<KeyEvent> for Message {
@@ -673,6 +1816,12 @@ impl From<KeyEvent> for Message {
 #[derive(Clone, Debug)]
 pub enum KeyboardEvent {
     UnsetFcitx5Hidden,
+    /// id of keyboard state, unit and the event.
+    Swipe(u8, u16, SwipeEvent),
+    UpdateStickyModifiers(bool),
+    /// Type a text without changing the state of keys.
+    TypeText(String),
+    UpdateCursorTrackpad(bool),
 }
 
 impl From<KeyboardEvent> for Message {
@@ -681,7 +1830,7 @@ impl From<KeyboardEvent> for Message {
     }
 }
 
-fn to_modifier_state(key_value: ThinKeyValue) -> ModifierState {
+fn to_modifier_state(key_value: &ThinKeyValue) -> ModifierState {
     match key_value.keysym() {
         Keysym::Shift_L | Keysym::Shift_R => ModifierState::Shift,
         Keysym::Caps_Lock => ModifierState::CapsLock,
@@ -693,6 +1842,23 @@ fn to_modifier_state(key_value: ThinKeyValue) -> ModifierState {
     }
 }
 
+fn on_key_action(action: &KeyAction) -> Message {
+    match action {
+        KeyAction::SwitchLayer(layer) => LayoutEvent::SwitchLayer(layer.clone()).into(),
+        KeyAction::HideKeyboard => WindowManagerEvent::HideKeyboard.into(),
+        KeyAction::NextIm => ImEvent::SelectNextIm.into(),
+        KeyAction::PrevIm => ImEvent::SelectPrevIm.into(),
+        KeyAction::ToggleSetting => LayoutEvent::ToggleSetting.into(),
+        KeyAction::TogglePlacement => WindowManagerEvent::TogglePlacement.into(),
+        // they change the state of the keyboard, see `KeyboardState::release_key`.
+        KeyAction::DeadKey(_) | KeyAction::Compose => Message::Nothing,
+    }
+}
+
+fn key_typed(keysym: Keysym) -> Task<Message> {
+    Task::done(PredictEvent::KeyTyped(keysym).into())
+}
+
 async fn on_key_release(
     s: &mut (dyn IFcitx5VirtualKeyboardBackendService + Send + Sync),
     key_state: &KeyState,
@@ -711,6 +1877,7 @@ async fn on_key_release(
     //
     // So I add keycodes to each key, if the key event contains a key code, I will
     // send the key code instead of key value to fcitx5.
+    let chord = key_state.selected_key_value.modifiers();
     let (keyval, keycode, modifiers) = if let Some(keycode) = key_state.selected_key_value.keycode()
     {
         // it looks like some input methods that needs keysym to work.
@@ -718,7 +1885,10 @@ async fn on_key_release(
         (keyval, keycode, 0)
     } else {
         let keyval = u32::from(key_state.selected_key_value.keysym());
-        (keyval, 0, modifiers)
+        let chord_modifiers = chord
+            .iter()
+            .fold(0, |m, c| m | chord_modifier_state(*c) as u32);
+        (keyval, 0, modifiers | chord_modifiers)
     };
     let send_shift = keycode < 0;
     let keycode = keycode.unsigned_abs() as u32;
@@ -734,6 +1904,18 @@ async fn on_key_release(
         }
         return Ok(Message::Nothing);
     }
+    // modifiers of a chord are pressed before the key, and they are sent by keycodes, so they
+    // go through modifier_workaround_keycodes as modifier keys do.
+    for modifier in chord {
+        s.process_key_event(
+            u32::from(modifier.keysym()),
+            modifier.keycode(),
+            0,
+            false,
+            pressed_time - 1,
+        )
+        .await?;
+    }
     if send_shift {
         // send a shift press event
         s.process_key_event(0, KEYCODE_LEFT_SHIFT, 0, false, pressed_time - 1)
@@ -751,5 +1933,139 @@ async fn on_key_release(
         s.process_key_event(0, KEYCODE_LEFT_SHIFT, 0, true, released_time)
             .await?;
     }
+    for modifier in chord.iter().rev() {
+        s.process_key_event(
+            u32::from(modifier.keysym()),
+            modifier.keycode(),
+            0,
+            true,
+            released_time,
+        )
+        .await?;
+    }
     Ok(Message::Nothing)
 }
+
+fn chord_modifier_state(modifier: ChordModifier) -> ModifierState {
+    match modifier {
+        ChordModifier::Shift => ModifierState::Shift,
+        ChordModifier::Ctrl => ModifierState::Ctrl,
+        ChordModifier::Alt => ModifierState::Alt,
+        ChordModifier::Super => ModifierState::Super,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dead_keys() -> HashMap<String, HashMap<char, String>> {
+        HashMap::from([(
+            "acute".to_string(),
+            HashMap::from([
+                ('e', "é".to_string()),
+                ('a', "á".to_string()),
+                (' ', "´".to_string()),
+            ]),
+        )])
+    }
+
+    fn compose_sequences() -> HashMap<String, String> {
+        HashMap::from([
+            ("oe".to_string(), "œ".to_string()),
+            ("o/".to_string(), "ø".to_string()),
+            ("<<".to_string(), "«".to_string()),
+        ])
+    }
+
+    fn combine_with(pending_compose: PendingCompose, c: Option<char>) -> Composed {
+        combine(pending_compose, c, &dead_keys(), &compose_sequences())
+    }
+
+    #[test]
+    fn dead_key_combines_a_char_in_its_table() {
+        let pending = PendingCompose::DeadKey("acute".to_string());
+        assert_eq!(
+            combine_with(pending, Some('e')),
+            Composed::Text("é".to_string())
+        );
+    }
+
+    #[test]
+    fn dead_key_types_its_accent_before_a_char_not_in_its_table() {
+        let pending = PendingCompose::DeadKey("acute".to_string());
+        assert_eq!(
+            combine_with(pending, Some('x')),
+            Composed::Unmatched("´".to_string())
+        );
+        let pending = PendingCompose::DeadKey("acute".to_string());
+        assert_eq!(
+            combine_with(pending, None),
+            Composed::Unmatched("´".to_string())
+        );
+        // a space is combined into the accent itself.
+        let pending = PendingCompose::DeadKey("acute".to_string());
+        assert_eq!(
+            combine_with(pending, Some(' ')),
+            Composed::Text("´".to_string())
+        );
+    }
+
+    #[test]
+    fn dead_key_without_an_accent_passes_through_a_char() {
+        let pending = PendingCompose::DeadKey("grave".to_string());
+        assert_eq!(
+            combine_with(pending, Some('e')),
+            Composed::Unmatched(String::new())
+        );
+        let dead_keys =
+            HashMap::from([("acute".to_string(), HashMap::from([('e', "é".to_string())]))]);
+        let pending = PendingCompose::DeadKey("acute".to_string());
+        assert_eq!(
+            combine(pending, Some('x'), &dead_keys, &compose_sequences()),
+            Composed::Unmatched(String::new())
+        );
+    }
+
+    #[test]
+    fn compose_waits_for_a_prefix_of_sequences() {
+        let pending = PendingCompose::Sequence(String::new());
+        assert_eq!(
+            combine_with(pending, Some('o')),
+            Composed::Pending(PendingCompose::Sequence("o".to_string()))
+        );
+    }
+
+    #[test]
+    fn compose_types_a_matched_sequence() {
+        let pending = PendingCompose::Sequence("o".to_string());
+        assert_eq!(
+            combine_with(pending, Some('/')),
+            Composed::Text("ø".to_string())
+        );
+        let pending = PendingCompose::Sequence("<".to_string());
+        assert_eq!(
+            combine_with(pending, Some('<')),
+            Composed::Text("«".to_string())
+        );
+    }
+
+    #[test]
+    fn compose_keeps_consumed_chars_of_an_unmatched_sequence() {
+        let pending = PendingCompose::Sequence("o".to_string());
+        assert_eq!(
+            combine_with(pending, Some('x')),
+            Composed::Unmatched("o".to_string())
+        );
+        let pending = PendingCompose::Sequence("o".to_string());
+        assert_eq!(
+            combine_with(pending, None),
+            Composed::Unmatched("o".to_string())
+        );
+        let pending = PendingCompose::Sequence(String::new());
+        assert_eq!(
+            combine_with(pending, Some('x')),
+            Composed::Unmatched(String::new())
+        );
+    }
+}
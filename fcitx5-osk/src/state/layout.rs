@@ -8,7 +8,8 @@ use iced::{
 
 use crate::{
     app::Message,
-    layout::{KeyAreaLayout, SettingLayout, ToElementCommonParams, ToolbarLayout},
+    config::ContentPurpose,
+    layout::{ClipboardLayout, KeyAreaLayout, SettingLayout, ToElementCommonParams, ToolbarLayout},
 };
 
 pub struct LayoutState {
@@ -20,13 +21,17 @@ pub struct LayoutState {
     toolbar_layout: ToolbarLayout,
     candidate_font: Font,
     key_area_layout: Rc<KeyAreaLayout>,
+    /// The active layer of the key area layout, it is synced with `KeyboardState`.
+    layer: Option<String>,
     setting_layout: SettingLayout,
     setting_shown: bool,
+    clipboard_layout: ClipboardLayout,
+    clipboard_shown: bool,
     max_width: u16,
 }
 
 impl LayoutState {
-    pub fn new(width: u16, key_area_layout: Rc<KeyAreaLayout>) -> Self {
+    pub fn new(width: u16, key_area_layout: Rc<KeyAreaLayout>, layer: Option<String>) -> Self {
         let mut res = Self {
             size: (0, 0),
             scale_factor: 1.0,
@@ -35,8 +40,11 @@ impl LayoutState {
             toolbar_layout: ToolbarLayout::new(key_area_layout.min_toolbar_height_u()),
             candidate_font: Default::default(),
             key_area_layout,
+            layer,
             setting_layout: SettingLayout,
             setting_shown: false,
+            clipboard_layout: ClipboardLayout,
+            clipboard_shown: false,
             max_width: width,
         };
         res.calculate_size();
@@ -45,7 +53,7 @@ impl LayoutState {
 
     pub fn unit_within(&self, width: u16) -> u16 {
         // plus two units of padding
-        let width_u = self.key_area_layout.width_u() + 2;
+        let width_u = self.key_area_layout.width_u(self.layer.as_deref()) + 2;
 
         let mut step = 1;
         loop {
@@ -73,7 +81,7 @@ impl LayoutState {
         // because of scaling issue, the actual window size is different from the one calculated in
         // this method.
         let unit = self.unit_within(self.max_width);
-        let key_area_size = self.key_area_layout.size(unit);
+        let key_area_size = self.key_area_layout.size(unit, self.layer.as_deref());
 
         self.unit = unit;
         let width = key_area_size.0 + 2 * unit;
@@ -128,16 +136,27 @@ impl LayoutState {
         &mut self,
         mut max_width: u16,
         mut key_area_layout: Rc<KeyAreaLayout>,
+        layer: Option<String>,
     ) -> Rc<KeyAreaLayout> {
         let new_min_toolbar_height_u = key_area_layout.min_toolbar_height_u();
         mem::swap(&mut self.key_area_layout, &mut key_area_layout);
         mem::swap(&mut self.max_width, &mut max_width);
+        self.layer = layer;
         self.toolbar_layout
             .update_height_u(new_min_toolbar_height_u);
         self.calculate_size();
         key_area_layout
     }
 
+    pub fn update_key_area_layer(&mut self, layer: Option<String>) {
+        self.layer = layer;
+        self.calculate_size();
+    }
+
+    pub fn key_area_layout(&self) -> &Rc<KeyAreaLayout> {
+        &self.key_area_layout
+    }
+
     pub fn update_candidate_font(&mut self, font: Font) {
         self.candidate_font = font;
     }
@@ -165,6 +184,7 @@ impl LayoutState {
                 self.key_area_layout.primary_text_size_u(),
             ));
 
+        let key_area_height = self.key_area_layout.height_u(self.layer.as_deref()) * self.unit;
         keyboard = if self.setting_shown {
             keyboard.push(
                 Container::new(self.setting_layout.to_element(
@@ -172,22 +192,49 @@ impl LayoutState {
                     self.unit,
                     self.key_area_layout.primary_text_size_u(),
                 ))
-                .height(self.key_area_layout.height_u() * self.unit),
+                .height(key_area_height),
+            )
+        } else if self.clipboard_shown && state.clipboard_history_enabled() {
+            keyboard.push(
+                Container::new(self.clipboard_layout.to_element(
+                    params,
+                    self.unit,
+                    self.key_area_layout.primary_text_size_u(),
+                ))
+                .height(key_area_height),
             )
         } else {
-            keyboard.push(self.key_area_layout.to_element(self.unit, params.state))
+            keyboard.push(self.key_area_layout.to_element(
+                self.unit,
+                self.layer.as_deref(),
+                params.state,
+            ))
         };
         // we let keyboard in a stack even there is no overlay, so the widget tree always has the
         // same level. Otherwise, the state will be clear if the level is changed.
         let mut stack = widget::stack![keyboard];
-        stack = stack.push_maybe(state.keyboard().popup_overlay(self.unit, self.size));
+        let key_preview = self.key_area_layout.key_preview() && state.key_preview_enabled();
+        stack = stack.push_maybe(
+            state
+                .keyboard()
+                .popup_overlay(self.unit, self.size, key_preview),
+        );
         stack.into()
     }
 
     pub fn on_event(&mut self, event: LayoutEvent) {
         match event {
-            LayoutEvent::ToggleSetting => self.setting_shown = !self.setting_shown,
-            LayoutEvent::SyncLayout => {}
+            LayoutEvent::ToggleSetting => {
+                self.setting_shown = !self.setting_shown;
+                self.clipboard_shown = false;
+            }
+            LayoutEvent::ToggleClipboard => {
+                self.clipboard_shown = !self.clipboard_shown;
+                self.setting_shown = false;
+            }
+            LayoutEvent::SyncLayout
+            | LayoutEvent::SwitchLayer(_)
+            | LayoutEvent::UpdateContentPurpose(_) => {}
         }
     }
 }
@@ -196,6 +243,12 @@ impl LayoutState {
 pub enum LayoutEvent {
     SyncLayout,
     ToggleSetting,
+    /// Show or hide the clipboard history in place of the key area.
+    ToggleClipboard,
+    /// Switch to a layer of the current layout, an empty name means the default layer.
+    SwitchLayer(String),
+    /// The purpose of the focused text field is changed, the layout mapped to it is used.
+    UpdateContentPurpose(ContentPurpose),
 }
 
 impl From<LayoutEvent> for Message {
@@ -0,0 +1,112 @@
+use std::mem;
+
+use iced::Task;
+use xkeysym::Keysym;
+
+use crate::{
+    app::{self, Message},
+    config::Config,
+    predict::{Dictionary, DictionaryWriter},
+    state::KeyboardEvent,
+};
+
+/// The max number of predicted words shown in the toolbar.
+const CANDIDATE_LIMIT: usize = 10;
+
+#[derive(Default)]
+pub struct PredictState {
+    dictionary: Dictionary,
+    writer: DictionaryWriter,
+    /// The word being typed.
+    word: String,
+    /// Whether the word being typed can be learned.
+    learn: bool,
+    candidates: Vec<String>,
+}
+
+impl PredictState {
+    pub fn load(&mut self, config: &Config) -> anyhow::Result<()> {
+        self.dictionary = Dictionary::load(config)?;
+        self.reset();
+        Ok(())
+    }
+
+    pub fn candidates(&self) -> &[String] {
+        &self.candidates
+    }
+
+    pub fn reset(&mut self) {
+        self.word.clear();
+        self.candidates.clear();
+    }
+
+    pub fn on_event(&mut self, event: PredictEvent, enabled: bool, learn: bool) -> Task<Message> {
+        if !enabled {
+            self.reset();
+            return Message::nothing();
+        }
+        self.learn = learn;
+        match event {
+            PredictEvent::KeyTyped(keysym) => {
+                match keysym.key_char() {
+                    _ if keysym == Keysym::BackSpace => {
+                        self.word.pop();
+                    }
+                    Some(c) if c.is_alphabetic() || c == '\'' => self.word.push(c),
+                    _ => return self.finish_word(),
+                }
+                self.candidates = self.dictionary.predict(&self.word, CANDIDATE_LIMIT);
+                Message::nothing()
+            }
+            PredictEvent::Select(index) => {
+                let Some(candidate) = self.candidates.get(index) else {
+                    return Message::nothing();
+                };
+                // keep the chars typed by the user, their cases may be different.
+                let rest: String = candidate
+                    .chars()
+                    .skip(self.word.chars().count())
+                    .chain([' '])
+                    .collect();
+                self.word = candidate.clone();
+                let task = Task::done(KeyboardEvent::TypeText(rest).into());
+                task.chain(self.finish_word())
+            }
+            PredictEvent::Reset => {
+                self.reset();
+                Message::nothing()
+            }
+        }
+    }
+
+    /// Learn the typed word and start a new one.
+    fn finish_word(&mut self) -> Task<Message> {
+        let word = mem::take(&mut self.word);
+        self.candidates.clear();
+        if !self.learn {
+            return Message::nothing();
+        }
+        let Some((path, content)) = self.dictionary.learn(&word) else {
+            return Message::nothing();
+        };
+        Task::perform(self.writer.write(path, content), |r| match r {
+            Err(e) => app::error_with_context(e, "Unable to save the user dictionary"),
+            Ok(_) => Message::Nothing,
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum PredictEvent {
+    /// A key typed by the user, it is used to track the word being typed.
+    KeyTyped(Keysym),
+    /// Complete the word by the selected candidate.
+    Select(usize),
+    Reset,
+}
+
+impl From<PredictEvent> for Message {
+    fn from(value: PredictEvent) -> Self {
+        Self::PredictEvent(value)
+    }
+}
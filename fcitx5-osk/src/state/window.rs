@@ -6,8 +6,9 @@ use tokio::time;
 use crate::{
     app::{MapTask, Message},
     config::{Config, IndicatorDisplay, Placement},
-    dbus::client::{
-        Fcitx5Services, Fcitx5VirtualKeyboardServiceExt, IFcitx5VirtualKeyboardService,
+    dbus::{
+        client::{Fcitx5Services, Fcitx5VirtualKeyboardServiceExt, IFcitx5VirtualKeyboardService},
+        server::ImPanelEvent,
     },
     layout::{self, KeyAreaLayout, ToElementCommonParams},
     state::{LayoutEvent, LayoutState, UpdateConfigEvent},
@@ -356,6 +357,7 @@ impl<WM> WindowManagerState<WM> {
         wm: WM,
         portrait: bool,
         key_area_layout: Rc<KeyAreaLayout>,
+        layer: Option<String>,
         fcitx5_services: Fcitx5Services,
     ) -> Self {
         let max_width = if portrait {
@@ -367,7 +369,7 @@ impl<WM> WindowManagerState<WM> {
             _screen_size: Default::default(),
             scale_factor: 1.,
             portrait,
-            layout: LayoutState::new(max_width, key_area_layout),
+            layout: LayoutState::new(max_width, key_area_layout, layer),
             keyboard_window_state: WindowState::new("keyboard"),
             indicator_window_state: WindowState::new("indicator"),
             placement: config.placement(),
@@ -400,6 +402,10 @@ impl<WM> WindowManagerState<WM> {
     pub fn update_candidate_font(&mut self, font: Font) {
         self.layout.update_candidate_font(font);
     }
+
+    pub fn key_area_layout(&self) -> &Rc<KeyAreaLayout> {
+        self.layout.key_area_layout()
+    }
 }
 
 impl<WM> WindowManagerState<WM>
@@ -748,11 +754,12 @@ where
         &mut self,
         max_width: u16,
         key_area_layout: Rc<KeyAreaLayout>,
+        layer: Option<String>,
     ) -> Option<Task<WM::Message>> {
         let old_size = self.size();
         let max_width = max_width.min(self.wm.screen_size().width as u16);
         self.layout
-            .update_key_area_layout(max_width, key_area_layout);
+            .update_key_area_layout(max_width, key_area_layout, layer);
         // resize if the size is changed
         let new_size = self.size();
         if new_size != old_size {
@@ -762,6 +769,18 @@ where
         }
     }
 
+    pub fn update_key_area_layer(&mut self, layer: Option<String>) -> Task<WM::Message> {
+        let old_size = self.size();
+        self.layout.update_key_area_layer(layer);
+        // resize if the size is changed
+        let new_size = self.size();
+        if new_size != old_size {
+            self.keyboard_window_state.resize(&mut self.wm, new_size)
+        } else {
+            Message::from_nothing()
+        }
+    }
+
     fn sync_output(&mut self) -> Task<WM::Message> {
         tracing::debug!("Sync output");
 
@@ -994,6 +1013,25 @@ where
             WindowManagerEvent::OutputChanged => self.sync_output(),
             WindowManagerEvent::OpenKeyboard => self.open_keyboard(),
             WindowManagerEvent::CloseKeyboard(source) => self.close_keyboard(source),
+            WindowManagerEvent::HideKeyboard => match self.indicator_display() {
+                IndicatorDisplay::Auto => self.open_indicator(),
+                IndicatorDisplay::AlwaysOn => self.close_keyboard(CloseOpSource::UserAction),
+                IndicatorDisplay::AlwaysOff => {
+                    if self.mode() == WindowManagerMode::KwinLockScreen {
+                        // the visibility is controlled by kwin on the lock screen.
+                        Task::done(Message::from(ImPanelEvent::NewVisibleRequest(false)).into())
+                    } else {
+                        self.close_keyboard(CloseOpSource::UserAction)
+                    }
+                }
+            },
+            WindowManagerEvent::TogglePlacement => {
+                let placement = match self.placement() {
+                    Placement::Dock => Placement::Float,
+                    Placement::Float => Placement::Dock,
+                };
+                Task::done(Message::from(UpdateConfigEvent::Placement(placement)).into())
+            }
             WindowManagerEvent::OpenIndicator => self.open_indicator(),
             WindowManagerEvent::UpdateMode(mode) => self.update_mode(mode),
             WindowManagerEvent::UpdatePlacement(placement) => self.update_placement(placement),
@@ -1068,7 +1106,12 @@ impl<WM> WindowManagerState<WM> {
 pub enum WindowManagerEvent {
     OpenKeyboard,
     CloseKeyboard(CloseOpSource),
+    /// Hide the keyboard as the hide button in the toolbar does, it depends on the indicator
+    /// display.
+    HideKeyboard,
     OpenIndicator,
+    /// Switch between dock and float.
+    TogglePlacement,
     UpdateMode(WindowManagerMode),
     UpdatePlacement(Placement),
     UpdateIndicatorDisplay(IndicatorDisplay),
@@ -1,15 +1,22 @@
 use core::hash::Hash;
-use std::{borrow::Cow, collections::HashMap, env, fmt::Display, path::PathBuf, rc::Rc};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env,
+    fmt::Display,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
 use crate::{
-    config::Config,
+    config::{Config, ContentPurpose},
     font,
     key_set::{Key, KeySet},
     layout::{KeyAreaLayout, KeyId},
     theme::Theme,
 };
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use figment::{
     providers::{Format, Toml},
     Figment,
@@ -39,6 +46,7 @@ pub struct Store {
     default_key_set: Rc<KeySet>,
     key_sets: HashMap<String, Rc<KeySet>>,
     im_layout_mapping: HashMap<String, HashMap<String, String>>,
+    content_purpose_layout_mapping: HashMap<String, HashMap<ContentPurpose, String>>,
     im_font_mapping: HashMap<String, Font>,
 }
 
@@ -75,6 +83,7 @@ impl Store {
             default_key_set,
             key_sets: Default::default(),
             im_layout_mapping: Default::default(),
+            content_purpose_layout_mapping: Default::default(),
             im_font_mapping: Default::default(),
         }
     }
@@ -113,6 +122,7 @@ impl Store {
             "key_sets",
         ))?;
         let im_layout_mapping = config.im_layout_mapping().clone();
+        let content_purpose_layout_mapping = config.content_purpose_layout_mapping().clone();
         let im_font_mapping = config
             .im_font_mapping()
             .iter()
@@ -135,10 +145,75 @@ impl Store {
             default_key_set,
             key_sets,
             im_layout_mapping,
+            content_purpose_layout_mapping,
             im_font_mapping,
         })
     }
 
+    /// Check all layouts, key sets and themes in the configured folders, and return the problems
+    /// found. Each file is parsed separately, so a broken file won't hide the problems of others.
+    pub fn validate(config: &Config) -> Result<Vec<String>> {
+        let mut problems = vec![];
+        problems.extend(check_confs::<String, Theme>(&xdg_config_folders_if_empty(
+            config.theme_folders(),
+            "themes",
+        ))?);
+        problems.extend(check_confs::<String, KeySet>(
+            &xdg_config_folders_if_empty(config.key_set_folders(), "key_sets"),
+        )?);
+        problems.extend(check_confs::<String, KeyAreaLayout>(
+            &xdg_config_folders_if_empty(config.key_area_layout_folders(), "layouts"),
+        )?);
+        if !problems.is_empty() {
+            // the store can't be loaded if any file is broken.
+            return Ok(problems);
+        }
+
+        let store = Self::load(config)?;
+        // only layouts from the configured folders, the builtin ones are not checked.
+        let mut key_area_layouts: Vec<_> = store.key_area_layouts.values().collect();
+        key_area_layouts.sort_unstable_by(|a, b| a.name().cmp(b.name()));
+        for key_area_layout in key_area_layouts {
+            problems.extend(key_area_layout.validate(&store));
+        }
+        let mut key_sets: Vec<_> = store.key_sets.values().collect();
+        key_sets.sort_unstable_by(|a, b| a.name().cmp(b.name()));
+        for key_set in key_sets {
+            problems.extend(key_set.validate());
+        }
+        for (orientation, mapping) in &store.im_layout_mapping {
+            for (im_name, layout_name) in mapping {
+                if store.key_area_layout_and_layer(layout_name).is_none() {
+                    problems.push(format!(
+                        "im_layout_mapping.{orientation}.{im_name}: layout[{layout_name}] is not found"
+                    ));
+                }
+            }
+        }
+        for (orientation, mapping) in &store.content_purpose_layout_mapping {
+            for (purpose, layout_name) in mapping {
+                if store.key_area_layout_and_layer(layout_name).is_none() {
+                    problems.push(format!(
+                        "content_purpose_layout_mapping.{orientation}.{purpose}: layout[{layout_name}] is not found"
+                    ));
+                }
+            }
+        }
+        Ok(problems)
+    }
+
+    /// The folders of layouts, key sets and themes to be loaded.
+    pub fn folders(config: &Config) -> Vec<PathBuf> {
+        [
+            xdg_config_folders_if_empty(config.key_area_layout_folders(), "layouts"),
+            xdg_config_folders_if_empty(config.key_set_folders(), "key_sets"),
+            xdg_config_folders_if_empty(config.theme_folders(), "themes"),
+        ]
+        .iter()
+        .flat_map(|f| f.iter().cloned())
+        .collect()
+    }
+
     pub fn theme_names(&self) -> &[String] {
         &self.theme_names
     }
@@ -160,7 +235,12 @@ impl Store {
     }
 
     pub fn key(&self, key_id: &KeyId) -> Option<&Key> {
-        let key_set = if let Some(key_set) = &key_id.key_set() {
+        self.key_set(key_id).keys().get(key_id.key_name())
+    }
+
+    /// The key set of a key, the default one is used if it is not found.
+    pub fn key_set(&self, key_id: &KeyId) -> &KeySet {
+        if let Some(key_set) = &key_id.key_set() {
             match self.key_sets.get(key_set) {
                 Some(key_set) => key_set,
                 None => {
@@ -170,8 +250,29 @@ impl Store {
             }
         } else {
             &self.default_key_set
+        }
+    }
+
+    /// Return a problem if the key can't be found. Unlike [`Store::key`], the default key set
+    /// isn't used when the key set is missing.
+    pub fn check_key_id(&self, key_id: &KeyId) -> Option<String> {
+        let key_set = if let Some(key_set) = &key_id.key_set() {
+            match self.key_sets.get(key_set) {
+                Some(key_set) => key_set,
+                None => return Some(format!("key_set[{}] is not found", key_set)),
+            }
+        } else {
+            &self.default_key_set
         };
-        key_set.keys().get(key_id.key_name())
+        if key_set.keys().contains_key(key_id.key_name()) {
+            None
+        } else {
+            Some(format!(
+                "key[{}] is not found in key_set[{}]",
+                key_id.key_name(),
+                key_set.name()
+            ))
+        }
     }
 
     pub fn font_by_im(&self, im_name: &str) -> Font {
@@ -181,7 +282,13 @@ impl Store {
             .unwrap_or_default()
     }
 
-    pub fn key_area_layout_by_im(&self, im_name: &str, portrait: bool) -> Rc<KeyAreaLayout> {
+    /// Return the layout and the initial layer of an input method. The value of
+    /// `im_layout_mapping` can be `layout_name` or `layout_name:layer_name`.
+    pub fn key_area_layout_by_im(
+        &self,
+        im_name: &str,
+        portrait: bool,
+    ) -> (Rc<KeyAreaLayout>, Option<String>) {
         let mapping = if portrait {
             self.im_layout_mapping.get("portrait")
         } else {
@@ -189,8 +296,42 @@ impl Store {
         };
         mapping
             .and_then(|m| m.get(im_name))
-            .and_then(|layout_name| self.key_area_layout(layout_name))
-            .unwrap_or_else(|| self.default_key_area_layout(portrait))
+            .and_then(|layout_name| self.key_area_layout_and_layer(layout_name))
+            .unwrap_or_else(|| (self.default_key_area_layout(portrait), None))
+    }
+
+    /// Return the layout and the initial layer of a content purpose, None if the purpose isn't
+    /// mapped.
+    pub fn key_area_layout_by_content_purpose(
+        &self,
+        purpose: ContentPurpose,
+        portrait: bool,
+    ) -> Option<(Rc<KeyAreaLayout>, Option<String>)> {
+        let mapping = if portrait {
+            self.content_purpose_layout_mapping.get("portrait")
+        } else {
+            self.content_purpose_layout_mapping.get("landscape")
+        };
+        mapping
+            .and_then(|m| m.get(&purpose))
+            .and_then(|layout_name| self.key_area_layout_and_layer(layout_name))
+    }
+
+    fn key_area_layout_and_layer(&self, name: &str) -> Option<(Rc<KeyAreaLayout>, Option<String>)> {
+        if let Some(key_area_layout) = self.key_area_layout(name) {
+            return Some((key_area_layout, None));
+        }
+        let (layout_name, layer) = name.rsplit_once(':')?;
+        match self.key_area_layout(layout_name) {
+            Some(key_area_layout) if key_area_layout.has_layer(layer) => {
+                Some((key_area_layout, Some(layer.to_string())))
+            }
+            Some(_) => {
+                tracing::warn!("layer[{layer}] is not found in layout[{layout_name}]");
+                None
+            }
+            None => None,
+        }
     }
 }
 
@@ -224,12 +365,8 @@ fn xdg_config_folders_if_empty<'a>(dir_paths: &'a [PathBuf], sub_dir: &str) -> C
     }
 }
 
-fn init_confs<'de, K, V>(dir_paths: &[PathBuf]) -> Result<HashMap<K, Rc<V>>>
-where
-    V: IdAndConfigPath<IdType = K> + Deserialize<'de>,
-    K: Clone + Display + Eq + Hash,
-{
-    let mut m = HashMap::<K, Rc<V>>::new();
+fn toml_files(dir_paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = vec![];
     for dir_path in dir_paths {
         if !dir_path.exists() {
             continue;
@@ -237,28 +374,61 @@ where
         for file in dir_path.read_dir()? {
             let file = file?;
             if let Some("toml") = file.path().extension().and_then(|p| p.to_str()) {
-                let figment = Figment::new().merge(Toml::file(file.path()));
-                let mut new: V = figment.extract()?;
-                new.set_path(file.path());
-                let new = Rc::new(new);
-                m.entry(new.id().clone())
-                    .and_modify(|old| {
-                        tracing::warn!(
-                            "Duplicate configs for id: {}, {:?} and {:?}, later will be used",
-                            old.id(),
-                            old.path(),
-                            new.path()
-                        );
-                        *old = new.clone();
-                    })
-                    .or_insert(new.clone());
-                tracing::debug!("Load {} from {:?}", new.id(), file.path());
+                files.push(file.path());
             }
         }
     }
+    Ok(files)
+}
+
+fn init_conf<'de, K, V>(path: &Path) -> Result<V>
+where
+    V: IdAndConfigPath<IdType = K> + Deserialize<'de>,
+{
+    let figment = Figment::new().merge(Toml::file(path));
+    let mut new: V = figment
+        .extract()
+        .with_context(|| format!("Unable to parse {:?}", path))?;
+    new.set_path(path);
+    Ok(new)
+}
+
+fn init_confs<'de, K, V>(dir_paths: &[PathBuf]) -> Result<HashMap<K, Rc<V>>>
+where
+    V: IdAndConfigPath<IdType = K> + Deserialize<'de>,
+    K: Clone + Display + Eq + Hash,
+{
+    let mut m = HashMap::<K, Rc<V>>::new();
+    for path in toml_files(dir_paths)? {
+        let new = Rc::new(init_conf::<K, V>(&path)?);
+        m.entry(new.id().clone())
+            .and_modify(|old| {
+                tracing::warn!(
+                    "Duplicate configs for id: {}, {:?} and {:?}, later will be used",
+                    old.id(),
+                    old.path(),
+                    new.path()
+                );
+                *old = new.clone();
+            })
+            .or_insert(new.clone());
+        tracing::debug!("Load {} from {:?}", new.id(), path);
+    }
     Ok(m)
 }
 
+/// Parse each file, and return the errors of broken files.
+fn check_confs<'de, K, V>(dir_paths: &[PathBuf]) -> Result<Vec<String>>
+where
+    V: IdAndConfigPath<IdType = K> + Deserialize<'de>,
+{
+    Ok(toml_files(dir_paths)?
+        .into_iter()
+        .filter_map(|path| init_conf::<K, V>(&path).err())
+        .map(|e| format!("{e:#}"))
+        .collect())
+}
+
 fn init_default<'de, T>(s: &str) -> Result<T>
 where
     T: Deserialize<'de>,
@@ -1,5 +1,6 @@
 mod key;
 mod movable;
+mod swipe;
 mod toggle;
 
 mod scrollable {
@@ -131,8 +132,9 @@ mod toggler {
     }
 }
 
-pub use key::{Key, KeyEvent, PopupKey};
+pub use key::{Direction, Key, KeyEvent, KeyHighlight, PopupKey};
 pub use movable::Movable;
 pub use scrollable::scrollable_style;
+pub use swipe::{Swipe, SwipeEvent};
 pub use toggle::{Toggle, ToggleCondition};
 pub use toggler::toggler_style;
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 use iced::{
     event::Status,
@@ -8,7 +8,7 @@ use iced::{
     },
     overlay,
     touch::{Event as TouchEvent, Finger as TouchFinger},
-    Border, Color, Element, Event, Length, Padding, Rectangle, Shadow, Size, Vector,
+    Border, Color, Element, Event, Length, Padding, Point, Rectangle, Shadow, Size, Vector,
 };
 use iced_futures::core::{
     layout, renderer,
@@ -19,7 +19,8 @@ use iced_futures::core::{
 /// Local state of the [`Key`].
 #[derive(Default)]
 struct KeyState {
-    fingers: HashSet<Option<TouchFinger>>,
+    /// Pressed fingers and where they are pressed.
+    fingers: HashMap<Option<TouchFinger>, Point>,
 }
 
 impl KeyState {
@@ -28,15 +29,46 @@ impl KeyState {
     }
 
     fn is_pressed(&self, finger: &Option<TouchFinger>) -> bool {
-        self.fingers.contains(finger)
+        self.fingers.contains_key(finger)
     }
 
-    fn finger_pressed(&mut self, finger: Option<TouchFinger>) {
-        self.fingers.insert(finger);
+    fn finger_pressed(&mut self, finger: Option<TouchFinger>, position: Point) {
+        self.fingers.insert(finger, position);
     }
 
-    fn finger_released(&mut self, finger: &Option<TouchFinger>) {
-        self.fingers.remove(finger);
+    fn finger_released(&mut self, finger: &Option<TouchFinger>) -> Option<Point> {
+        self.fingers.remove(finger)
+    }
+}
+
+/// The main direction of a drag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// The direction and the distance of a drag, None if it doesn't move.
+    pub fn of_drag(drag: Vector) -> Option<(Self, f32)> {
+        let distance = drag.x.hypot(drag.y);
+        if distance == 0. {
+            return None;
+        }
+        let direction = if drag.x.abs() >= drag.y.abs() {
+            if drag.x > 0. {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if drag.y > 0. {
+            Direction::Down
+        } else {
+            Direction::Up
+        };
+        Some((direction, distance))
     }
 }
 
@@ -46,6 +78,24 @@ pub struct KeyEvent {
     pub cancelled: bool,
     pub finger: Option<TouchFinger>,
     pub bounds: Rectangle,
+    /// From where the key is pressed to where it is released or moved to.
+    pub drag: Vector,
+}
+
+impl KeyEvent {
+    /// The direction and the distance of the drag.
+    pub fn drag_direction(&self) -> Option<(Direction, f32)> {
+        Direction::of_drag(self.drag)
+    }
+}
+
+/// The state of a key that is still active after it is released, like a latched modifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyHighlight {
+    /// It will be cleared after the next key.
+    Latched,
+    /// It will be kept until the key is pressed again.
+    Locked,
 }
 
 pub trait AsThemeRef {
@@ -58,25 +108,35 @@ impl AsThemeRef for iced::Theme {
     }
 }
 
-/// A widget works like MouseArea, Emit messages on mouse press/release events and finger press/lift/lost events.
-pub struct Key<'a, Message, PressCb, ReleaseCb, Theme = iced::Theme, Renderer = iced::Renderer> {
+/// A widget works like MouseArea, Emit messages on mouse press/release events and finger press/lift/lost events. Moves of a pressed pointer are emitted too if [`Key::on_move_with`] is set.
+pub struct Key<
+    'a,
+    Message,
+    PressCb,
+    ReleaseCb,
+    MoveCb = DummyCb<Message>,
+    Theme = iced::Theme,
+    Renderer = iced::Renderer,
+> {
     content: Element<'a, Message, Theme, Renderer>,
     width: Length,
     height: Length,
     padding: Padding,
     on_press_with: Option<PressCb>,
     on_release_with: Option<ReleaseCb>,
+    on_move_with: Option<MoveCb>,
     border_radius: f32,
+    highlight: Option<KeyHighlight>,
 }
 
-impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
-    Key<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
+impl<'a, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>
+    Key<'a, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>
 {
     /// The callback for getting a message on a press event.
     pub fn on_press_with<NewPressCb>(
         self,
         cb: Option<NewPressCb>,
-    ) -> Key<'a, Message, NewPressCb, ReleaseCb, Theme, Renderer> {
+    ) -> Key<'a, Message, NewPressCb, ReleaseCb, MoveCb, Theme, Renderer> {
         let Key {
             content,
             width,
@@ -84,7 +144,9 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
             padding,
             on_press_with: _on_press_with,
             on_release_with,
+            on_move_with,
             border_radius,
+            highlight,
         } = self;
         Key {
             content,
@@ -93,7 +155,9 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
             padding,
             on_press_with: cb,
             on_release_with,
+            on_move_with,
             border_radius,
+            highlight,
         }
     }
 
@@ -101,7 +165,7 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
     pub fn on_release_with<NewReleaseCb>(
         self,
         cb: Option<NewReleaseCb>,
-    ) -> Key<'a, Message, PressCb, NewReleaseCb, Theme, Renderer> {
+    ) -> Key<'a, Message, PressCb, NewReleaseCb, MoveCb, Theme, Renderer> {
         let Key {
             content,
             width,
@@ -109,7 +173,9 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
             padding,
             on_press_with,
             on_release_with: _on_release_with,
+            on_move_with,
             border_radius,
+            highlight,
         } = self;
         Key {
             content,
@@ -118,7 +184,39 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
             padding,
             on_press_with,
             on_release_with: cb,
+            on_move_with,
+            border_radius,
+            highlight,
+        }
+    }
+
+    /// The callback for getting a message when a pressed pointer moves, the drag is from where
+    /// the key is pressed.
+    pub fn on_move_with<NewMoveCb>(
+        self,
+        cb: Option<NewMoveCb>,
+    ) -> Key<'a, Message, PressCb, ReleaseCb, NewMoveCb, Theme, Renderer> {
+        let Key {
+            content,
+            width,
+            height,
+            padding,
+            on_press_with,
+            on_release_with,
+            on_move_with: _on_move_with,
+            border_radius,
+            highlight,
+        } = self;
+        Key {
+            content,
+            width,
+            height,
+            padding,
+            on_press_with,
+            on_release_with,
+            on_move_with: cb,
             border_radius,
+            highlight,
         }
     }
 
@@ -136,12 +234,17 @@ impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
         self.padding = padding;
         self
     }
+
+    pub fn highlight(mut self, highlight: Option<KeyHighlight>) -> Self {
+        self.highlight = highlight;
+        self
+    }
 }
 
 pub type DummyCb<Message> = fn(KeyEvent) -> Message;
 
 impl<'a, Message, Theme, Renderer>
-    Key<'a, Message, DummyCb<Message>, DummyCb<Message>, Theme, Renderer>
+    Key<'a, Message, DummyCb<Message>, DummyCb<Message>, DummyCb<Message>, Theme, Renderer>
 where
     Renderer: renderer::Renderer,
 {
@@ -159,17 +262,20 @@ where
             padding: Default::default(),
             on_press_with: None,
             on_release_with: None,
+            on_move_with: None,
             border_radius,
+            highlight: None,
         }
     }
 }
 
-impl<Message, PressCb, ReleaseCb, Theme, Renderer> Widget<Message, Theme, Renderer>
-    for Key<'_, Message, PressCb, ReleaseCb, Theme, Renderer>
+impl<Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for Key<'_, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>
 where
     Message: Clone,
     PressCb: 'static + Fn(KeyEvent) -> Message,
     ReleaseCb: 'static + Fn(KeyEvent) -> Message,
+    MoveCb: 'static + Fn(KeyEvent) -> Message,
     Theme: AsThemeRef,
     Renderer: renderer::Renderer,
 {
@@ -276,10 +382,15 @@ where
         viewport: &Rectangle,
     ) {
         let state: &KeyState = tree.state.downcast_ref();
+        let palette = theme.as_ref().extended_palette();
         let background = if state.has_finger_pressed() {
-            theme.as_ref().extended_palette().primary.strong.color
+            palette.primary.strong.color
         } else {
-            theme.as_ref().extended_palette().background.base.color
+            match self.highlight {
+                Some(KeyHighlight::Locked) => palette.primary.base.color,
+                Some(KeyHighlight::Latched) => palette.primary.weak.color,
+                None => palette.background.base.color,
+            }
         };
         renderer.fill_quad(
             renderer::Quad {
@@ -317,18 +428,19 @@ where
     }
 }
 
-impl<'a, Message, PressCb, ReleaseCb, Theme, Renderer>
-    From<Key<'a, Message, PressCb, ReleaseCb, Theme, Renderer>>
+impl<'a, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>
+    From<Key<'a, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>>
     for Element<'a, Message, Theme, Renderer>
 where
     Message: 'a + Clone,
     PressCb: 'static + Fn(KeyEvent) -> Message,
     ReleaseCb: 'static + Fn(KeyEvent) -> Message,
+    MoveCb: 'static + Fn(KeyEvent) -> Message,
     Theme: 'a + AsThemeRef,
     Renderer: 'a + renderer::Renderer,
 {
     fn from(
-        key: Key<'a, Message, PressCb, ReleaseCb, Theme, Renderer>,
+        key: Key<'a, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>,
     ) -> Element<'a, Message, Theme, Renderer> {
         Element::new(key)
     }
@@ -336,8 +448,8 @@ where
 
 /// Processes the given [`Event`] and updates the [`KeyState`] of an [`Key`]
 /// accordingly.
-fn update<Message, PressCb, ReleaseCb, Theme, Renderer>(
-    widget: &mut Key<'_, Message, PressCb, ReleaseCb, Theme, Renderer>,
+fn update<Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>(
+    widget: &mut Key<'_, Message, PressCb, ReleaseCb, MoveCb, Theme, Renderer>,
     tree: &mut Tree,
     event: Event,
     layout: Layout<'_>,
@@ -348,6 +460,7 @@ where
     Message: Clone,
     PressCb: 'static + Fn(KeyEvent) -> Message,
     ReleaseCb: 'static + Fn(KeyEvent) -> Message,
+    MoveCb: 'static + Fn(KeyEvent) -> Message,
 {
     if widget.on_press_with.is_none() && widget.on_release_with.is_none() {
         return Status::Ignored;
@@ -355,6 +468,27 @@ where
 
     let state: &mut KeyState = tree.state.downcast_mut();
 
+    let moved = match event {
+        Event::Mouse(MouseEvent::CursorMoved { position }) => Some((None, position)),
+        Event::Touch(TouchEvent::FingerMoved { id, position }) => Some((Some(id), position)),
+        _ => None,
+    };
+    if let Some((finger, position)) = moved {
+        if let (Some(pressed_position), Some(cb)) =
+            (state.fingers.get(&finger), widget.on_move_with.as_ref())
+        {
+            shell.publish(cb(KeyEvent {
+                pressed: true,
+                cancelled: false,
+                finger,
+                bounds: layout.bounds(),
+                drag: position - *pressed_position,
+            }));
+        }
+        // moving doesn't change the state of keys, others may need it too.
+        return Status::Ignored;
+    }
+
     let (pressed, cancelled, finger, position) = match event {
         Event::Mouse(MouseEvent::ButtonPressed(MouseButton::Left)) => {
             (true, false, None, cursor.position())
@@ -388,35 +522,38 @@ where
                     position,
                     finger
                 );
-                if !state.has_finger_pressed() {
-                    shell.publish(cb(KeyEvent {
-                        pressed,
-                        cancelled,
-                        finger,
-                        bounds,
-                    }));
-                }
-                state.finger_pressed(finger);
+                // every finger is published, so several fingers can hold the same key.
+                shell.publish(cb(KeyEvent {
+                    pressed,
+                    cancelled,
+                    finger,
+                    bounds,
+                    drag: Vector::ZERO,
+                }));
+                state.finger_pressed(finger, position);
                 return Status::Captured;
             }
         }
     } else if state.is_pressed(&finger) {
         if let Some(cb) = widget.on_release_with.as_ref() {
-            state.finger_released(&finger);
+            let pressed_position = state.finger_released(&finger);
             tracing::trace!(
                 "key[{:?}] is released by finger {:?}, pressed: {}",
                 layout.bounds(),
                 finger,
                 state.fingers.len(),
             );
-            if !state.has_finger_pressed() {
-                shell.publish(cb(KeyEvent {
-                    pressed,
-                    cancelled,
-                    finger,
-                    bounds: layout.bounds(),
-                }));
-            }
+            let drag = pressed_position
+                .zip(position)
+                .map(|(from, to)| to - from)
+                .unwrap_or(Vector::ZERO);
+            shell.publish(cb(KeyEvent {
+                pressed,
+                cancelled,
+                finger,
+                bounds: layout.bounds(),
+                drag,
+            }));
             return Status::Captured;
         }
     }
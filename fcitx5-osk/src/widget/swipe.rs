@@ -0,0 +1,402 @@
+use std::collections::HashMap;
+
+use iced::{
+    event::Status,
+    mouse::{
+        Button as MouseButton, Cursor as MouseCursor, Event as MouseEvent,
+        Interaction as MouseInteraction,
+    },
+    overlay,
+    touch::{Event as TouchEvent, Finger as TouchFinger},
+    Element, Event, Length, Point, Rectangle, Size, Vector,
+};
+use iced_futures::core::{
+    layout, renderer,
+    widget::{tree, Operation, Tree},
+    Clipboard, Layout, Shell, Widget,
+};
+
+struct SwipePath {
+    points: Vec<Point>,
+    swiping: bool,
+    /// The index of the move bounds the pointer is in.
+    move_bounds: Option<usize>,
+}
+
+/// Local state of the [`Swipe`].
+#[derive(Default)]
+struct SwipeState {
+    paths: HashMap<Option<TouchFinger>, SwipePath>,
+    /// Paths released while other pointers are still pressed, they may be a multi-pointer
+    /// gesture.
+    released: Vec<(Option<TouchFinger>, SwipePath)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum SwipeEvent {
+    /// The pointer has moved far enough, the key pressed by this pointer should be cancelled.
+    Started(Option<TouchFinger>),
+    /// The pointer is released, the points are relative to the top left corner of the content.
+    Finished(Option<TouchFinger>, Vec<Point>),
+    /// The pointer has moved but it isn't a swipe, the point is relative to the top left corner
+    /// of the content.
+    Moved(Option<TouchFinger>, Point),
+    /// The pointer has moved farther than the threshold and it is released, but it isn't a
+    /// swipe. Only emitted if gestures are enabled.
+    Dragged(Option<TouchFinger>, Vec<Point>),
+    /// Several pointers have moved farther than the threshold together, and all of them are
+    /// released, the keys pressed by these pointers should be cancelled. Only emitted if
+    /// gestures are enabled.
+    MultiFinished(Vec<(Option<TouchFinger>, Vec<Point>)>),
+}
+
+/// A widget tracks the path of each pointer moving across its content. Emit messages once a
+/// pointer has moved farther than the threshold and after it is released.
+pub struct Swipe<'a, Message, SwipeCb, Theme = iced::Theme, Renderer = iced::Renderer> {
+    content: Element<'a, Message, Theme, Renderer>,
+    enabled: bool,
+    track_moves: bool,
+    move_bounds: Vec<Rectangle>,
+    gestures: bool,
+    threshold: f32,
+    on_swipe: SwipeCb,
+}
+
+impl<'a, Message, SwipeCb, Theme, Renderer> Swipe<'a, Message, SwipeCb, Theme, Renderer>
+where
+    Renderer: renderer::Renderer,
+{
+    /// Creates a [`Swipe`] with the given content.
+    pub fn new(
+        content: impl Into<Element<'a, Message, Theme, Renderer>>,
+        on_swipe: SwipeCb,
+        enabled: bool,
+    ) -> Self {
+        let content = content.into();
+        Self {
+            content,
+            enabled,
+            track_moves: false,
+            move_bounds: vec![],
+            gestures: false,
+            threshold: 0.,
+            on_swipe,
+        }
+    }
+
+    /// The distance a pointer should move before it is treated as a swipe.
+    pub fn threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Emit [`SwipeEvent::Moved`] while a pointer moves before it is treated as a swipe.
+    pub fn track_moves(mut self, track_moves: bool) -> Self {
+        self.track_moves = track_moves;
+        self
+    }
+
+    /// Emit [`SwipeEvent::Moved`] only if a pointer moves into other bounds, e.g. the bounds of
+    /// keys. The bounds are relative to the top left corner of the content.
+    pub fn move_bounds(mut self, move_bounds: Vec<Rectangle>) -> Self {
+        self.move_bounds = move_bounds;
+        self
+    }
+
+    fn move_bounds_index(&self, point: Point) -> Option<usize> {
+        self.move_bounds.iter().position(|b| b.contains(point))
+    }
+
+    /// Emit [`SwipeEvent::Dragged`] and [`SwipeEvent::MultiFinished`], so gestures can be
+    /// recognized.
+    pub fn gestures(mut self, gestures: bool) -> Self {
+        self.gestures = gestures;
+        self
+    }
+}
+
+impl<Message, SwipeCb, Theme, Renderer> Widget<Message, Theme, Renderer>
+    for Swipe<'_, Message, SwipeCb, Theme, Renderer>
+where
+    Message: Clone,
+    Renderer: renderer::Renderer,
+    SwipeCb: Fn(SwipeEvent) -> Message,
+{
+    fn tag(&self) -> tree::Tag {
+        tree::Tag::of::<SwipeState>()
+    }
+
+    fn state(&self) -> tree::State {
+        tree::State::new(SwipeState::default())
+    }
+
+    fn children(&self) -> Vec<Tree> {
+        vec![Tree::new(&self.content)]
+    }
+
+    fn diff(&self, tree: &mut Tree) {
+        tree.diff_children(std::slice::from_ref(&self.content));
+    }
+
+    fn size(&self) -> Size<Length> {
+        self.content.as_widget().size()
+    }
+
+    fn layout(
+        &self,
+        tree: &mut Tree,
+        renderer: &Renderer,
+        limits: &layout::Limits,
+    ) -> layout::Node {
+        self.content
+            .as_widget()
+            .layout(&mut tree.children[0], renderer, limits)
+    }
+
+    fn operate(
+        &self,
+        tree: &mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        operation: &mut dyn Operation,
+    ) {
+        self.content
+            .as_widget()
+            .operate(&mut tree.children[0], layout, renderer, operation);
+    }
+
+    fn on_event(
+        &mut self,
+        tree: &mut Tree,
+        event: Event,
+        layout: Layout<'_>,
+        cursor: MouseCursor,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        shell: &mut Shell<'_, Message>,
+        viewport: &Rectangle,
+    ) -> Status {
+        let state: &mut SwipeState = tree.state.downcast_mut();
+        if !self.enabled && !self.track_moves && !self.gestures {
+            state.paths.clear();
+            state.released.clear();
+        } else {
+            update(self, state, &event, layout, cursor, shell);
+        }
+
+        self.content.as_widget_mut().on_event(
+            &mut tree.children[0],
+            event,
+            layout,
+            cursor,
+            renderer,
+            clipboard,
+            shell,
+            viewport,
+        )
+    }
+
+    fn mouse_interaction(
+        &self,
+        tree: &Tree,
+        layout: Layout<'_>,
+        cursor: MouseCursor,
+        viewport: &Rectangle,
+        renderer: &Renderer,
+    ) -> MouseInteraction {
+        self.content.as_widget().mouse_interaction(
+            &tree.children[0],
+            layout,
+            cursor,
+            viewport,
+            renderer,
+        )
+    }
+
+    fn draw(
+        &self,
+        tree: &Tree,
+        renderer: &mut Renderer,
+        theme: &Theme,
+        renderer_style: &renderer::Style,
+        layout: Layout<'_>,
+        cursor: MouseCursor,
+        viewport: &Rectangle,
+    ) {
+        self.content.as_widget().draw(
+            &tree.children[0],
+            renderer,
+            theme,
+            renderer_style,
+            layout,
+            cursor,
+            viewport,
+        );
+    }
+
+    fn overlay<'b>(
+        &'b mut self,
+        tree: &'b mut Tree,
+        layout: Layout<'_>,
+        renderer: &Renderer,
+        translation: Vector,
+    ) -> Option<overlay::Element<'b, Message, Theme, Renderer>> {
+        self.content
+            .as_widget_mut()
+            .overlay(&mut tree.children[0], layout, renderer, translation)
+    }
+}
+
+impl<'a, Message, SwipeCb, Theme, Renderer> From<Swipe<'a, Message, SwipeCb, Theme, Renderer>>
+    for Element<'a, Message, Theme, Renderer>
+where
+    Message: 'a + Clone,
+    Theme: 'a,
+    Renderer: 'a + renderer::Renderer,
+    SwipeCb: 'a + Fn(SwipeEvent) -> Message,
+{
+    fn from(
+        swipe: Swipe<'a, Message, SwipeCb, Theme, Renderer>,
+    ) -> Element<'a, Message, Theme, Renderer> {
+        Element::new(swipe)
+    }
+}
+
+/// Processes the given [`Event`] and updates the [`SwipeState`] of a [`Swipe`] accordingly.
+///
+/// It runs before the content, so [`SwipeEvent::Finished`] will be published before the release
+/// event of a key.
+fn update<Message, SwipeCb, Theme, Renderer>(
+    widget: &Swipe<'_, Message, SwipeCb, Theme, Renderer>,
+    state: &mut SwipeState,
+    event: &Event,
+    layout: Layout<'_>,
+    cursor: MouseCursor,
+    shell: &mut Shell<'_, Message>,
+) where
+    Renderer: renderer::Renderer,
+    SwipeCb: Fn(SwipeEvent) -> Message,
+{
+    let (pressed, moved, pointer, position) = match *event {
+        Event::Mouse(MouseEvent::ButtonPressed(MouseButton::Left)) => {
+            (true, false, None, cursor.position())
+        }
+        Event::Mouse(MouseEvent::ButtonReleased(MouseButton::Left)) => (false, false, None, None),
+        Event::Mouse(MouseEvent::CursorMoved { position }) => (false, true, None, Some(position)),
+        Event::Touch(TouchEvent::FingerPressed { id, position }) => {
+            (true, false, Some(id), Some(position))
+        }
+        Event::Touch(TouchEvent::FingerMoved { id, position }) => {
+            (false, true, Some(id), Some(position))
+        }
+        Event::Touch(TouchEvent::FingerLifted { id, .. }) => (false, false, Some(id), None),
+        Event::Touch(TouchEvent::FingerLost { id, .. }) => {
+            // the key will be cancelled too, nothing to be sent.
+            state.paths.remove(&Some(id));
+            if state.paths.is_empty() {
+                state.released.clear();
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    let bounds = layout.bounds();
+    let origin = bounds.position();
+    if pressed {
+        if let Some(position) = position.filter(|p| bounds.contains(*p)) {
+            let point = Point::ORIGIN + (position - origin);
+            state.paths.insert(
+                pointer,
+                SwipePath {
+                    points: vec![point],
+                    swiping: false,
+                    move_bounds: widget.move_bounds_index(point),
+                },
+            );
+        }
+    } else if moved {
+        if let (Some(path), Some(position)) = (state.paths.get_mut(&pointer), position) {
+            let point = Point::ORIGIN + (position - origin);
+            if widget.enabled && !path.swiping && path.points[0].distance(point) > widget.threshold
+            {
+                path.swiping = true;
+                shell.publish((widget.on_swipe)(SwipeEvent::Started(pointer)));
+            }
+            if widget.track_moves && !path.swiping {
+                let move_bounds = widget.move_bounds_index(point);
+                if widget.move_bounds.is_empty() || move_bounds != path.move_bounds {
+                    path.move_bounds = move_bounds;
+                    shell.publish((widget.on_swipe)(SwipeEvent::Moved(pointer, point)));
+                }
+            }
+            path.points.push(point);
+        }
+    } else if let Some(path) = state.paths.remove(&pointer) {
+        let far = path.swiping
+            || path
+                .points
+                .last()
+                .is_some_and(|p| path.points[0].distance(*p) > widget.threshold);
+        if widget.gestures && far {
+            if !state.paths.is_empty() {
+                // wait for other pointers, the key of this pointer should be cancelled now.
+                if !path.swiping {
+                    shell.publish((widget.on_swipe)(SwipeEvent::Started(pointer)));
+                }
+                state.released.push((pointer, path));
+                return;
+            }
+            if !state.released.is_empty() {
+                let paths = state
+                    .released
+                    .drain(..)
+                    .map(|(pointer, p)| (pointer, p.points))
+                    .chain([(pointer, path.points)])
+                    .collect();
+                shell.publish((widget.on_swipe)(SwipeEvent::MultiFinished(paths)));
+                return;
+            }
+        }
+        if state.paths.is_empty() {
+            // the pointers waited for don't have a partner.
+            match state.released.len() {
+                0 => {}
+                1 => {
+                    let (pointer, path) = state.released.remove(0);
+                    publish_path(widget, shell, pointer, path);
+                }
+                _ => {
+                    let paths = state
+                        .released
+                        .drain(..)
+                        .map(|(pointer, p)| (pointer, p.points))
+                        .collect();
+                    shell.publish((widget.on_swipe)(SwipeEvent::MultiFinished(paths)));
+                }
+            }
+        }
+        if far {
+            publish_path(widget, shell, pointer, path);
+        }
+    }
+}
+
+/// Publish the path of a released pointer which has moved farther than the threshold.
+fn publish_path<Message, SwipeCb, Theme, Renderer>(
+    widget: &Swipe<'_, Message, SwipeCb, Theme, Renderer>,
+    shell: &mut Shell<'_, Message>,
+    pointer: Option<TouchFinger>,
+    path: SwipePath,
+) where
+    SwipeCb: Fn(SwipeEvent) -> Message,
+{
+    if path.swiping {
+        shell.publish((widget.on_swipe)(SwipeEvent::Finished(
+            pointer,
+            path.points,
+        )));
+    } else if widget.gestures {
+        shell.publish((widget.on_swipe)(SwipeEvent::Dragged(pointer, path.points)));
+    }
+}